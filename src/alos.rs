@@ -0,0 +1,186 @@
+extern crate nalgebra as na;
+
+use na::{Matrix2, UnitVector2, Vector2};
+
+use crate::guidance_law::{
+    body_to_ned, comp_path_rates, validate_gains, GuidanceError, GuidanceLaw, GuidanceState,
+    OutputMode, YawRateMode,
+};
+use crate::ilos::ssa;
+use crate::los::Lookahead;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ALOSState {
+    pub yaw_angle: f64,
+    pub yaw_rate: f64,
+    pub crab_angle_estimate: f64,
+    pub lookahead: Lookahead,
+    pub adaptation_gain: f64,
+}
+
+#[allow(non_snake_case)]
+#[derive(Clone)]
+pub struct ALOS {
+    yaw_angle: f64,
+    yaw_rate: f64,
+    crab_angle_estimate: f64,
+    S: Matrix2<f64>,
+    lookahead: Lookahead,
+    adaptation_gain: f64,
+    max_crab_angle: f64,
+    yaw_rate_mode: YawRateMode,
+    curvature: f64,
+    vel: Vector2<f64>,
+}
+
+impl Default for ALOS {
+    fn default() -> Self {
+        Self::new(Lookahead::Constant(1.0), 0.01, 30.0_f64.to_radians())
+    }
+}
+
+impl ALOS {
+    pub fn new(lookahead: Lookahead, adaptation_gain: f64, max_crab_angle: f64) -> ALOS {
+        ALOS {
+            yaw_angle: 0.0,
+            yaw_rate: 0.0,
+            crab_angle_estimate: 0.0,
+            S: Matrix2::new(0.0, -1.0, 1.0, 0.0),
+            lookahead,
+            adaptation_gain,
+            max_crab_angle,
+            yaw_rate_mode: YawRateMode::FiniteDifference,
+            curvature: 0.0,
+            vel: Vector2::zeros(),
+        }
+    }
+
+    // Parameter projection keeping the estimate inside [-max_crab_angle, max_crab_angle]
+    fn project(&self, crab_angle_dot: f64) -> f64 {
+        if self.crab_angle_estimate.abs() >= self.max_crab_angle
+            && self.crab_angle_estimate * crab_angle_dot > 0.0
+        {
+            0.0
+        } else {
+            crab_angle_dot
+        }
+    }
+
+    pub fn set_lookahead(&mut self, lookahead: Lookahead) -> Result<(), GuidanceError> {
+        lookahead.validate()?;
+        self.lookahead = lookahead;
+        Ok(())
+    }
+
+    pub fn get_lookahead(&self) -> Lookahead {
+        self.lookahead
+    }
+
+    pub fn set_adaptation_gain(&mut self, adaptation_gain: f64) {
+        self.adaptation_gain = adaptation_gain;
+    }
+
+    pub fn get_adaptation_gain(&self) -> f64 {
+        self.adaptation_gain
+    }
+
+    pub fn get_crab_angle_estimate(&self) -> f64 {
+        self.crab_angle_estimate
+    }
+}
+
+impl GuidanceLaw for ALOS {
+    fn update(
+        &mut self,
+        pos: &Vector2<f64>,
+        pos_d: &Vector2<f64>,
+        tau: &UnitVector2<f64>,
+        dt: f64,
+    ) {
+        let cross_track_err = (pos - pos_d).dot(&(self.S * tau.into_inner()));
+        let delta = self.lookahead.comp_lookahead(cross_track_err);
+        let path_angle = tau[1].atan2(tau[0]);
+
+        let crab_angle_dot = self.project(
+            self.adaptation_gain * delta / (delta.powi(2) + cross_track_err.powi(2)).sqrt()
+                * cross_track_err,
+        );
+
+        let yaw_angle_prev = self.yaw_angle;
+        self.yaw_angle =
+            ssa(path_angle - self.crab_angle_estimate - (cross_track_err / delta).atan());
+        self.yaw_rate = match self.yaw_rate_mode {
+            YawRateMode::FiniteDifference => ssa(ssa(self.yaw_angle) - ssa(yaw_angle_prev)) / dt,
+            YawRateMode::Analytic => {
+                let (path_angle_rate, cross_track_err_rate) =
+                    comp_path_rates(tau, cross_track_err, self.curvature, &self.vel);
+                path_angle_rate
+                    - crab_angle_dot
+                    - self.lookahead.comp_los_angle_derivative(cross_track_err)
+                        * cross_track_err_rate
+            }
+        };
+
+        self.crab_angle_estimate += crab_angle_dot * dt;
+    }
+
+    fn get_references(&self) -> (f64, f64) {
+        (self.yaw_angle, self.yaw_rate)
+    }
+
+    fn set_gains(&mut self, prop_gain: f64, integral_gain: f64) -> Result<(), GuidanceError> {
+        validate_gains(prop_gain, integral_gain)?;
+        self.lookahead.set_nominal(1.0 / prop_gain)?;
+        self.adaptation_gain = integral_gain;
+        Ok(())
+    }
+
+    fn get_gains(&self) -> (f64, f64) {
+        (1.0 / self.lookahead.get_nominal(), self.adaptation_gain)
+    }
+
+    fn reset(&mut self) {
+        self.crab_angle_estimate = 0.0;
+    }
+
+    fn save_state(&self) -> GuidanceState {
+        GuidanceState::ALOS(ALOSState {
+            yaw_angle: self.yaw_angle,
+            yaw_rate: self.yaw_rate,
+            crab_angle_estimate: self.crab_angle_estimate,
+            lookahead: self.lookahead,
+            adaptation_gain: self.adaptation_gain,
+        })
+    }
+
+    fn restore_state(&mut self, state: &GuidanceState) -> Result<(), GuidanceError> {
+        match state {
+            GuidanceState::ALOS(state) => {
+                self.yaw_angle = state.yaw_angle;
+                self.yaw_rate = state.yaw_rate;
+                self.crab_angle_estimate = state.crab_angle_estimate;
+                self.lookahead = state.lookahead;
+                self.adaptation_gain = state.adaptation_gain;
+                Ok(())
+            }
+            _ => Err(GuidanceError::StateMismatch),
+        }
+    }
+
+    fn set_velocity(&mut self, yaw: f64, vel: &Vector2<f64>) {
+        self.vel = body_to_ned(yaw, vel);
+    }
+
+    fn set_curvature(&mut self, curvature: f64) {
+        self.curvature = curvature;
+    }
+
+    fn set_yaw_rate_mode(&mut self, mode: YawRateMode) {
+        self.yaw_rate_mode = mode;
+    }
+
+    fn get_output_mode(&self) -> OutputMode {
+        OutputMode::Heading
+    }
+}
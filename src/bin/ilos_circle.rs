@@ -1,4 +1,4 @@
-use ilos_guidance::{paths::circle::Circle, ilos::ILOS, zenoh_tools::*};
+use ilos_guidance::{paths::circle::Circle, zenoh_tools::*};
 
 use zenoh::prelude::r#async::*;
 // use serde_derive::{Deserialize, Serialize};
@@ -34,6 +34,8 @@ struct Args {
     /// ILOS integral gain
     #[arg(short, long, default_value_t = 0.01)]
     ki: f64,
+    #[command(flatten)]
+    guidance: GuidanceArgs,
 }
 
 #[tokio::main]
@@ -53,8 +55,7 @@ async fn main() {
     println!("Controller frequency: {}", freq);
     println!("Controller period: {}", 1 / freq);
 
-    let ilos = ILOS::new(kp, ki);
-    let arc_ilos = Arc::new(Mutex::new(ilos));
+    let (arc_ilos, timer_options) = setup_guidance(args.guidance, kp, ki);
 
     let session = zenoh::open(config::default())
         .res()
@@ -62,21 +63,22 @@ async fn main() {
         .unwrap()
         .into_arc();
 
-    let pos_measured: Option<Vector2<f64>> = None;
-    let arc_pos = Arc::new(Mutex::new(pos_measured));
-    let pos_measured = arc_pos.clone();
+    let state_measured: Option<VesselState> = None;
+    let arc_state = Arc::new(Mutex::new(state_measured));
+    let state_measured = arc_state.clone();
 
     let an_session = session.clone();
 
     tokio::spawn(async move {
-        position_subscriber(an_session, topic_name, pos_measured).await;
+        position_subscriber(an_session, topic_name, state_measured).await;
     });
 
     let an_session = session.clone();
     let an_ilos = arc_ilos.clone();
+    let an_gain_schedule = timer_options.gain_schedule.clone();
 
     tokio::spawn(async move {
-        update_ilos_parameters(an_session, param_topic, an_ilos).await;
+        update_ilos_parameters(an_session, param_topic, an_ilos, an_gain_schedule).await;
     });
 
     // let circle = Circle::new(radius, center, clockwise);
@@ -85,7 +87,7 @@ async fn main() {
 
     let an_session = session.clone();
     let an_ilos = arc_ilos.clone();
-    let pos_measured = arc_pos.clone();
+    let state_measured = arc_state.clone();
     let dt = 1.0 / (freq as f64);
     println!("dt: {}", dt);
 
@@ -93,10 +95,11 @@ async fn main() {
         ilos_timer(
             an_session,
             output_topic_name,
-            pos_measured,
+            state_measured,
             an_ilos,
             circle,
             dt,
+            timer_options,
         )
         .await;
     })
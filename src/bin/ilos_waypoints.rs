@@ -1,9 +1,20 @@
-use ilos_guidance::{ilos::ILOS, paths::waypoint_path::WaypointPath, zenoh_tools::*};
+use ilos_guidance::{
+    paths::{
+        dubins::DubinsPath,
+        path::{EndBehavior, Path},
+        search::{SearchPattern, SearchPatternType},
+        spline::CubicSpline,
+        survey::LawnmowerSurvey,
+        waypoint_path::{CornerType, Waypoint, WaypointPath},
+    },
+    zenoh_tools::*,
+};
 
 use zenoh::prelude::r#async::*;
 // use serde_derive::{Deserialize, Serialize};
 // use std::fmt;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 extern crate nalgebra as na;
 use na::Vector2;
@@ -32,11 +43,94 @@ struct Args {
     /// ILOS integral gain
     #[arg(long, default_value_t = 0.01)]
     ki: f64,
+    #[command(flatten)]
+    guidance: GuidanceArgs,
+    /// Connect the last waypoint back to the first one
+    #[arg(long)]
+    closed: bool,
+    /// Shrink turn radii that do not fit on short legs
+    #[arg(long)]
+    auto_radius: bool,
+    /// Distance to the current segment above which the closest segment is searched globally
+    #[arg(long)]
+    relocalization_threshold: Option<f64>,
+    /// Margin by which another segment must be closer before relocalizing to it
+    #[arg(long, default_value_t = 1.0)]
+    relocalization_hysteresis: f64,
+    /// Shape of the turns at the waypoints
+    #[arg(long, value_enum, default_value_t = CornerType::Circle)]
+    corner_type: CornerType,
+    /// Track a cubic spline through the waypoints instead of lines and fillet arcs
+    #[arg(long, conflicts_with_all = [
+        "closed",
+        "auto_radius",
+        "end_behavior",
+        "relocalization_threshold",
+        "relocalization_hysteresis",
+        "corner_type",
+    ])]
+    spline: bool,
+    /// Behavior once the final waypoint has been passed
+    #[arg(long, value_enum, default_value_t = EndBehavior::Stop)]
+    end_behavior: EndBehavior,
+    /// Plan a Dubins path from the first odometry pose to the goal x, y, heading (rad) with the
+    /// turn radius instead of following the waypoints
+    #[arg(long, num_args = 3, allow_negative_numbers = true)]
+    dubins_goal: Option<Vec<f64>>,
+    /// Run a search pattern from the datum instead of following the waypoints
+    #[arg(long, value_enum)]
+    search_pattern: Option<SearchPatternType>,
+    /// Datum x, y of the search pattern
+    #[arg(long, num_args = 2, allow_negative_numbers = true, default_values_t = [0.0, 0.0])]
+    datum: Vec<f64>,
+    /// Track spacing of the search pattern
+    #[arg(long, default_value_t = 10.0)]
+    track_spacing: f64,
+    /// Number of legs of the search pattern
+    #[arg(long, default_value_t = 9)]
+    legs: usize,
+    /// Direction of the first leg of the search pattern (rad)
+    #[arg(long, default_value_t = 0.0)]
+    search_orientation: f64,
+    /// Turn clockwise in the search pattern
+    #[arg(long)]
+    search_clockwise: bool,
+    /// Join the lines of a survey with half circles instead of fillets
+    #[arg(long)]
+    u_turns: bool,
+    /// Reset the guidance law state on path segment transitions
+    #[arg(long)]
+    reset_on_segment_change: bool,
 }
 
+// either plain points, waypoints with turn radii and leg metadata or a survey pattern
 #[derive(Debug, Deserialize, Serialize)]
 struct Waypoints {
+    #[serde(default)]
     points: Vec<Vector2<f64>>,
+    #[serde(default)]
+    waypoints: Vec<Waypoint>,
+    survey: Option<LawnmowerSurvey>,
+}
+
+impl Waypoints {
+    fn into_waypoints(self) -> Vec<Waypoint> {
+        match self.survey {
+            Some(survey) => survey
+                .comp_waypoints()
+                .unwrap_or_else(|e| panic!("Invalid survey: {}", e))
+                .into_iter()
+                .map(|pos| Waypoint {
+                    turn_radius: Some(survey.turn_radius),
+                    ..Waypoint::new(pos)
+                })
+                .collect(),
+            None if self.waypoints.is_empty() => {
+                self.points.into_iter().map(Waypoint::new).collect()
+            }
+            None => self.waypoints,
+        }
+    }
 }
 
 #[tokio::main]
@@ -50,19 +144,14 @@ async fn main() {
     let kp = args.kp;
     let ki = args.ki;
 
-    let f = std::fs::File::open("waypoints.yaml").expect("Could not open file.");
-    let waypoints: Waypoints = serde_yaml::from_reader(f).unwrap();
-
-    println!("Waypoints: {:?}", waypoints);
-
     let param_topic = "ilos/params".to_string();
 
     println!("Subscribing to topic: {}", topic_name);
     println!("Controller frequency: {}", freq);
     println!("Controller period: {}", 1 / freq);
 
-    let ilos = ILOS::new(kp, ki);
-    let arc_ilos = Arc::new(Mutex::new(ilos));
+    let (arc_ilos, mut timer_options) = setup_guidance(args.guidance, kp, ki);
+    timer_options.reset_on_segment_change = args.reset_on_segment_change;
 
     let session = zenoh::open(config::default())
         .res()
@@ -70,28 +159,114 @@ async fn main() {
         .unwrap()
         .into_arc();
 
-    let pos_measured: Option<Vector2<f64>> = None;
-    let arc_pos = Arc::new(Mutex::new(pos_measured));
-    let pos_measured = arc_pos.clone();
+    let state_measured: Option<VesselState> = None;
+    let arc_state = Arc::new(Mutex::new(state_measured));
+    let state_measured = arc_state.clone();
 
     let an_session = session.clone();
 
     tokio::spawn(async move {
-        position_subscriber(an_session, topic_name, pos_measured).await;
+        position_subscriber(an_session, topic_name, state_measured).await;
     });
 
     let an_session = session.clone();
     let an_ilos = arc_ilos.clone();
+    let an_gain_schedule = timer_options.gain_schedule.clone();
 
     tokio::spawn(async move {
-        update_ilos_parameters(an_session, param_topic, an_ilos).await;
+        update_ilos_parameters(an_session, param_topic, an_ilos, an_gain_schedule).await;
     });
 
-    let wp_path = WaypointPath::new(waypoints.points, circle_radius);
+    let path: Box<dyn Path + Send> = match args.dubins_goal {
+        Some(goal) => {
+            println!("Waiting for odometry to plan the Dubins path");
+            let (pos, yaw) = loop {
+                let pose = arc_state
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .map(|state| (state.pos, state.yaw));
+                match pose {
+                    Some(pose) => break pose,
+                    None => tokio::time::sleep(Duration::from_millis(100)).await,
+                }
+            };
+            let goal_pos = Vector2::new(goal[0], goal[1]);
+            let path = DubinsPath::try_new(pos, yaw, goal_pos, goal[2], circle_radius)
+                .unwrap_or_else(|e| panic!("Invalid Dubins path: {}", e));
+            println!(
+                "Dubins path: {:?}, length: {}",
+                path.get_word(),
+                path.total_length()
+            );
+            Box::new(path)
+        }
+        None => {
+            let waypoints: Waypoints = match args.search_pattern {
+                Some(pattern) => {
+                    let datum = Vector2::new(args.datum[0], args.datum[1]);
+                    let mut search =
+                        SearchPattern::new(pattern, datum, args.track_spacing, args.legs);
+                    search.orientation = args.search_orientation;
+                    search.clockwise = args.search_clockwise;
+                    Waypoints {
+                        points: search.comp_waypoints(),
+                        waypoints: Vec::new(),
+                        survey: None,
+                    }
+                }
+                None => {
+                    let f = std::fs::File::open("waypoints.yaml").expect("Could not open file.");
+                    serde_yaml::from_reader(f).unwrap()
+                }
+            };
+
+            println!("Waypoints: {:?}", waypoints);
+
+            match waypoints.survey {
+                Some(survey) if args.u_turns => Box::new(
+                    survey
+                        .to_u_turn_path()
+                        .unwrap_or_else(|e| panic!("Invalid survey: {}", e)),
+                ),
+                _ => {
+                    let waypoints = waypoints.into_waypoints();
+                    if args.spline {
+                        Box::new(
+                            CubicSpline::try_new(
+                                waypoints.iter().map(|waypoint| waypoint.pos).collect(),
+                            )
+                            .unwrap_or_else(|e| panic!("Invalid spline: {}", e)),
+                        )
+                    } else {
+                        let mut wp_path = if args.auto_radius {
+                            WaypointPath::try_from_waypoints_auto_radius(
+                                waypoints,
+                                circle_radius,
+                                args.closed,
+                            )
+                        } else {
+                            WaypointPath::try_from_waypoints(waypoints, circle_radius, args.closed)
+                        }
+                        .unwrap_or_else(|e| panic!("Invalid waypoint path: {}", e));
+                        if args.auto_radius {
+                            println!("Effective turn radii: {:?}", wp_path.get_turn_radii());
+                        }
+                        wp_path.set_corner_type(args.corner_type);
+                        wp_path.set_end_behavior(args.end_behavior);
+                        if let Some(threshold) = args.relocalization_threshold {
+                            wp_path.set_relocalization(threshold, args.relocalization_hysteresis);
+                        }
+                        Box::new(wp_path)
+                    }
+                }
+            }
+        }
+    };
 
     let an_session = session.clone();
     let an_ilos = arc_ilos.clone();
-    let pos_measured = arc_pos.clone();
+    let state_measured = arc_state.clone();
     let dt = 1.0 / (freq as f64);
     println!("dt: {}", dt);
 
@@ -99,10 +274,11 @@ async fn main() {
         ilos_timer(
             an_session,
             output_topic_name,
-            pos_measured,
+            state_measured,
             an_ilos,
-            wp_path,
+            path,
             dt,
+            timer_options,
         )
         .await;
     })
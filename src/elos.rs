@@ -0,0 +1,206 @@
+extern crate nalgebra as na;
+
+use na::{Matrix2, UnitVector2, Vector2};
+
+use crate::guidance_law::{
+    body_to_ned, comp_path_rates, validate_gains, GuidanceError, GuidanceLaw, GuidanceState,
+    OutputMode, YawRateMode,
+};
+use crate::ilos::ssa;
+use crate::los::Lookahead;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ELOSState {
+    pub yaw_angle: f64,
+    pub yaw_rate: f64,
+    pub cross_track_err_estimate: Option<f64>,
+    pub drift_estimate: f64,
+    pub lookahead: Lookahead,
+    pub observer_gain: f64,
+}
+
+#[allow(non_snake_case)]
+#[derive(Clone)]
+pub struct ELOS {
+    yaw_angle: f64,
+    yaw_rate: f64,
+    cross_track_err_estimate: Option<f64>,
+    drift_estimate: f64,
+    yaw_measured: f64,
+    speed_measured: f64,
+    S: Matrix2<f64>,
+    lookahead: Lookahead,
+    observer_gain: f64,
+    min_speed: f64,
+    yaw_rate_mode: YawRateMode,
+    curvature: f64,
+    vel: Vector2<f64>,
+}
+
+impl Default for ELOS {
+    fn default() -> Self {
+        Self::new(Lookahead::Constant(1.0), 1.0)
+    }
+}
+
+impl ELOS {
+    pub fn new(lookahead: Lookahead, observer_gain: f64) -> ELOS {
+        ELOS {
+            yaw_angle: 0.0,
+            yaw_rate: 0.0,
+            cross_track_err_estimate: None,
+            drift_estimate: 0.0,
+            yaw_measured: 0.0,
+            speed_measured: 0.0,
+            S: Matrix2::new(0.0, -1.0, 1.0, 0.0),
+            lookahead,
+            observer_gain,
+            min_speed: 0.1,
+            yaw_rate_mode: YawRateMode::FiniteDifference,
+            curvature: 0.0,
+            vel: Vector2::zeros(),
+        }
+    }
+
+    pub fn set_lookahead(&mut self, lookahead: Lookahead) -> Result<(), GuidanceError> {
+        lookahead.validate()?;
+        self.lookahead = lookahead;
+        Ok(())
+    }
+
+    pub fn get_lookahead(&self) -> Lookahead {
+        self.lookahead
+    }
+
+    pub fn set_observer_gain(&mut self, observer_gain: f64) {
+        self.observer_gain = observer_gain;
+    }
+
+    pub fn get_observer_gain(&self) -> f64 {
+        self.observer_gain
+    }
+
+    pub fn get_drift_estimate(&self) -> f64 {
+        self.drift_estimate
+    }
+}
+
+impl GuidanceLaw for ELOS {
+    fn update(
+        &mut self,
+        pos: &Vector2<f64>,
+        pos_d: &Vector2<f64>,
+        tau: &UnitVector2<f64>,
+        dt: f64,
+    ) {
+        let cross_track_err = (pos - pos_d).dot(&(self.S * tau.into_inner()));
+        let delta = self.lookahead.comp_lookahead(cross_track_err);
+        let path_angle = tau[1].atan2(tau[0]);
+        let speed = self.speed_measured;
+
+        // Reduced-order observer for e_dot = U sin(psi - path_angle) + g, with g the unknown drift
+        let w = self.observer_gain;
+        let cross_track_err_estimate = self.cross_track_err_estimate.unwrap_or(cross_track_err);
+        let estimation_err = cross_track_err_estimate - cross_track_err;
+
+        let cross_track_err_estimate_dot = speed * ssa(self.yaw_measured - path_angle).sin()
+            + self.drift_estimate
+            - 2.0 * w * estimation_err;
+        let drift_estimate_dot = -w.powi(2) * estimation_err;
+
+        let (drift_compensation, drift_compensation_dot) =
+            if speed > self.min_speed && self.drift_estimate.abs() < speed {
+                (
+                    (self.drift_estimate / speed).asin(),
+                    drift_estimate_dot / (speed.powi(2) - self.drift_estimate.powi(2)).sqrt(),
+                )
+            } else if speed > self.min_speed {
+                ((self.drift_estimate / speed).clamp(-1.0, 1.0).asin(), 0.0)
+            } else {
+                (0.0, 0.0)
+            };
+
+        let yaw_angle_prev = self.yaw_angle;
+        self.yaw_angle = ssa(path_angle - (cross_track_err / delta).atan() - drift_compensation);
+        self.yaw_rate = match self.yaw_rate_mode {
+            YawRateMode::FiniteDifference => ssa(ssa(self.yaw_angle) - ssa(yaw_angle_prev)) / dt,
+            YawRateMode::Analytic => {
+                let (path_angle_rate, cross_track_err_rate) =
+                    comp_path_rates(tau, cross_track_err, self.curvature, &self.vel);
+                path_angle_rate
+                    - self.lookahead.comp_los_angle_derivative(cross_track_err)
+                        * cross_track_err_rate
+                    - drift_compensation_dot
+            }
+        };
+
+        self.cross_track_err_estimate =
+            Some(cross_track_err_estimate + cross_track_err_estimate_dot * dt);
+        self.drift_estimate += drift_estimate_dot * dt;
+    }
+
+    fn get_references(&self) -> (f64, f64) {
+        (self.yaw_angle, self.yaw_rate)
+    }
+
+    // the observer gain is set on its own, it is not tuned like an integral gain
+    fn set_gains(&mut self, prop_gain: f64, integral_gain: f64) -> Result<(), GuidanceError> {
+        validate_gains(prop_gain, integral_gain)?;
+        self.lookahead.set_nominal(1.0 / prop_gain)?;
+        Ok(())
+    }
+
+    fn get_gains(&self) -> (f64, f64) {
+        (1.0 / self.lookahead.get_nominal(), 0.0)
+    }
+
+    fn reset(&mut self) {
+        self.cross_track_err_estimate = None;
+        self.drift_estimate = 0.0;
+    }
+
+    fn save_state(&self) -> GuidanceState {
+        GuidanceState::ELOS(ELOSState {
+            yaw_angle: self.yaw_angle,
+            yaw_rate: self.yaw_rate,
+            cross_track_err_estimate: self.cross_track_err_estimate,
+            drift_estimate: self.drift_estimate,
+            lookahead: self.lookahead,
+            observer_gain: self.observer_gain,
+        })
+    }
+
+    fn restore_state(&mut self, state: &GuidanceState) -> Result<(), GuidanceError> {
+        match state {
+            GuidanceState::ELOS(state) => {
+                self.yaw_angle = state.yaw_angle;
+                self.yaw_rate = state.yaw_rate;
+                self.cross_track_err_estimate = state.cross_track_err_estimate;
+                self.drift_estimate = state.drift_estimate;
+                self.lookahead = state.lookahead;
+                self.observer_gain = state.observer_gain;
+                Ok(())
+            }
+            _ => Err(GuidanceError::StateMismatch),
+        }
+    }
+
+    fn set_velocity(&mut self, yaw: f64, vel: &Vector2<f64>) {
+        self.yaw_measured = yaw;
+        self.speed_measured = vel.norm();
+        self.vel = body_to_ned(yaw, vel);
+    }
+
+    fn set_curvature(&mut self, curvature: f64) {
+        self.curvature = curvature;
+    }
+
+    fn set_yaw_rate_mode(&mut self, mode: YawRateMode) {
+        self.yaw_rate_mode = mode;
+    }
+
+    fn get_output_mode(&self) -> OutputMode {
+        OutputMode::Heading
+    }
+}
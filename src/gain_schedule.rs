@@ -0,0 +1,112 @@
+use crate::paths::path::PathSegment;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GainBreakpoint {
+    pub speed: f64,
+    pub kp: f64,
+    pub ki: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GainSchedule {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub line: Vec<GainBreakpoint>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub circle: Vec<GainBreakpoint>,
+}
+
+impl GainSchedule {
+    // Gains linearly interpolated in surge speed, held constant outside the breakpoints.
+    // None if there are no breakpoints for the segment type.
+    pub fn comp_gains(&self, speed: f64, segment: PathSegment) -> Option<(f64, f64)> {
+        let breakpoints = match segment {
+            PathSegment::Line => &self.line,
+            PathSegment::Circle => &self.circle,
+        };
+
+        let lower = breakpoints
+            .iter()
+            .filter(|b| b.speed <= speed)
+            .max_by(|a, b| a.speed.total_cmp(&b.speed));
+        let upper = breakpoints
+            .iter()
+            .filter(|b| b.speed >= speed)
+            .min_by(|a, b| a.speed.total_cmp(&b.speed));
+
+        match (lower, upper) {
+            (Some(lower), Some(upper)) if upper.speed > lower.speed => {
+                let s = (speed - lower.speed) / (upper.speed - lower.speed);
+                Some((
+                    lower.kp + s * (upper.kp - lower.kp),
+                    lower.ki + s * (upper.ki - lower.ki),
+                ))
+            }
+            (Some(b), _) | (None, Some(b)) => Some((b.kp, b.ki)),
+            (None, None) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comp_schedule() -> GainSchedule {
+        GainSchedule {
+            line: vec![
+                GainBreakpoint {
+                    speed: 2.0,
+                    kp: 0.2,
+                    ki: 0.02,
+                },
+                GainBreakpoint {
+                    speed: 1.0,
+                    kp: 0.4,
+                    ki: 0.04,
+                },
+            ],
+            circle: vec![GainBreakpoint {
+                speed: 1.0,
+                kp: 0.5,
+                ki: 0.0,
+            }],
+        }
+    }
+
+    fn assert_gains(gains: Option<(f64, f64)>, kp: f64, ki: f64) {
+        let (gains_kp, gains_ki) = gains.unwrap();
+        assert!((gains_kp - kp).abs() < 1e-12 && (gains_ki - ki).abs() < 1e-12);
+    }
+
+    #[test]
+    fn interpolates_between_unsorted_breakpoints() {
+        let schedule = comp_schedule();
+        assert_gains(schedule.comp_gains(1.5, PathSegment::Line), 0.3, 0.03);
+        assert_gains(schedule.comp_gains(1.75, PathSegment::Line), 0.25, 0.025);
+        assert_gains(schedule.comp_gains(2.0, PathSegment::Line), 0.2, 0.02);
+    }
+
+    #[test]
+    fn holds_the_gains_outside_the_breakpoints() {
+        let schedule = comp_schedule();
+        assert_gains(schedule.comp_gains(0.0, PathSegment::Line), 0.4, 0.04);
+        assert_gains(schedule.comp_gains(5.0, PathSegment::Line), 0.2, 0.02);
+        assert_gains(schedule.comp_gains(-1.0, PathSegment::Circle), 0.5, 0.0);
+        assert_gains(schedule.comp_gains(3.0, PathSegment::Circle), 0.5, 0.0);
+    }
+
+    #[test]
+    fn no_gains_without_breakpoints() {
+        let schedule = GainSchedule {
+            circle: Vec::new(),
+            ..comp_schedule()
+        };
+        assert_eq!(schedule.comp_gains(1.0, PathSegment::Circle), None);
+        assert_eq!(
+            GainSchedule::default().comp_gains(1.0, PathSegment::Line),
+            None
+        );
+    }
+}
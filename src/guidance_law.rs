@@ -0,0 +1,260 @@
+extern crate nalgebra as na;
+
+use na::{Rotation2, UnitVector2, Vector2};
+
+use std::fmt;
+
+use crate::alos::ALOSState;
+use crate::elos::ELOSState;
+use crate::ilos::{ssa, AntiWindup, ILOSState};
+use crate::los::{LOSState, Lookahead};
+
+const MIN_SIDESLIP_SPEED: f64 = 0.1;
+const MIN_PATH_DISTANCE_FACTOR: f64 = 0.1;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "zenoh", derive(clap::ValueEnum))]
+pub enum OutputMode {
+    #[default]
+    Course,
+    Heading,
+}
+
+impl OutputMode {
+    // converts a desired course to this mode, heading = course - sideslip
+    pub fn comp_yaw(&self, course: f64, vel: &Vector2<f64>) -> f64 {
+        match self {
+            OutputMode::Course => course,
+            OutputMode::Heading => {
+                if vel.norm() < MIN_SIDESLIP_SPEED {
+                    course
+                } else {
+                    ssa(course - vel[1].atan2(vel[0]))
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "zenoh", derive(clap::ValueEnum))]
+pub enum YawRateMode {
+    // differentiate consecutive yaw references
+    #[default]
+    FiniteDifference,
+    // curvature feedforward and derivative of the LOS angle with respect to the cross-track error
+    Analytic,
+}
+
+pub fn body_to_ned(yaw: f64, vel: &Vector2<f64>) -> Vector2<f64> {
+    Rotation2::new(yaw) * vel
+}
+
+// Rate of the path tangent angle and of the cross-track error for a vessel moving with the
+// NED velocity vel. The path-parameter rate follows from projecting vel onto the tangent.
+pub fn comp_path_rates(
+    tau: &UnitVector2<f64>,
+    cross_track_err: f64,
+    curvature: f64,
+    vel: &Vector2<f64>,
+) -> (f64, f64) {
+    let normal = Vector2::new(-tau[1], tau[0]);
+    let path_speed =
+        vel.dot(tau) / (1.0 - curvature * cross_track_err).max(MIN_PATH_DISTANCE_FACTOR);
+    (curvature * path_speed, vel.dot(&normal))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GuidanceError {
+    InvalidTimeStep(f64),
+    InvalidGains(f64, f64),
+    InvalidLookahead(Lookahead),
+    InvalidAntiWindup(AntiWindup),
+    NonFiniteInput,
+    NonFiniteOutput,
+    StateMismatch,
+}
+
+impl fmt::Display for GuidanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GuidanceError::InvalidTimeStep(dt) => write!(f, "invalid time step: {}", dt),
+            GuidanceError::InvalidGains(kp, ki) => write!(f, "invalid gains: ({}, {})", kp, ki),
+            GuidanceError::InvalidLookahead(lookahead) => {
+                write!(f, "invalid lookahead: {:?}", lookahead)
+            }
+            GuidanceError::InvalidAntiWindup(anti_windup) => {
+                write!(f, "invalid anti-windup settings: {:?}", anti_windup)
+            }
+            GuidanceError::NonFiniteInput => write!(f, "non-finite position or tangent input"),
+            GuidanceError::NonFiniteOutput => write!(f, "non-finite yaw references"),
+            GuidanceError::StateMismatch => {
+                write!(f, "state snapshot belongs to a different guidance law")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GuidanceError {}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GuidanceState {
+    ILOS(ILOSState),
+    LOS(LOSState),
+    ALOS(ALOSState),
+    ELOS(ELOSState),
+}
+
+impl GuidanceState {
+    // false once the references or an estimate of the law have diverged
+    pub fn is_finite(&self) -> bool {
+        match self {
+            GuidanceState::ILOS(state) => {
+                state.yaw_angle.is_finite()
+                    && state.yaw_rate.is_finite()
+                    && state.integral_state.is_finite()
+            }
+            GuidanceState::LOS(state) => state.yaw_angle.is_finite() && state.yaw_rate.is_finite(),
+            GuidanceState::ALOS(state) => {
+                state.yaw_angle.is_finite()
+                    && state.yaw_rate.is_finite()
+                    && state.crab_angle_estimate.is_finite()
+            }
+            GuidanceState::ELOS(state) => {
+                state.yaw_angle.is_finite()
+                    && state.yaw_rate.is_finite()
+                    && state.cross_track_err_estimate.iter().all(|x| x.is_finite())
+                    && state.drift_estimate.is_finite()
+            }
+        }
+    }
+}
+
+// prop_gain has to be positive, it is the inverse lookahead distance of the LOS laws
+pub fn validate_gains(prop_gain: f64, integral_gain: f64) -> Result<(), GuidanceError> {
+    if !(prop_gain.is_finite()
+        && prop_gain > 0.0
+        && integral_gain.is_finite()
+        && integral_gain >= 0.0)
+    {
+        return Err(GuidanceError::InvalidGains(prop_gain, integral_gain));
+    }
+    Ok(())
+}
+
+pub trait GuidanceLaw {
+    fn update(&mut self, pos: &Vector2<f64>, pos_d: &Vector2<f64>, tau: &UnitVector2<f64>, dt: f64);
+    // Like update, but leaves the state untouched and returns an error on invalid inputs or
+    // outputs. The update runs on the law itself, which is restored from a snapshot of its state
+    // if the resulting references are not finite.
+    fn try_update(
+        &mut self,
+        pos: &Vector2<f64>,
+        pos_d: &Vector2<f64>,
+        tau: &UnitVector2<f64>,
+        dt: f64,
+    ) -> Result<(), GuidanceError> {
+        if !dt.is_finite() || dt <= 0.0 {
+            return Err(GuidanceError::InvalidTimeStep(dt));
+        }
+        let (prop_gain, integral_gain) = self.get_gains();
+        validate_gains(prop_gain, integral_gain)?;
+        if !(pos.iter().all(|x| x.is_finite())
+            && pos_d.iter().all(|x| x.is_finite())
+            && tau.iter().all(|x| x.is_finite()))
+        {
+            return Err(GuidanceError::NonFiniteInput);
+        }
+
+        let state = self.save_state();
+        self.update(pos, pos_d, tau, dt);
+        let (yaw, yaw_rate) = self.get_references();
+        if !(yaw.is_finite() && yaw_rate.is_finite() && self.save_state().is_finite()) {
+            self.restore_state(&state)?;
+            return Err(GuidanceError::NonFiniteOutput);
+        }
+        Ok(())
+    }
+    fn get_references(&self) -> (f64, f64);
+    // prop_gain is the inverse lookahead distance, the meaning of integral_gain depends on the law.
+    // Leaves the gains untouched if they are invalid.
+    fn set_gains(&mut self, prop_gain: f64, integral_gain: f64) -> Result<(), GuidanceError>;
+    fn get_gains(&self) -> (f64, f64);
+    fn reset(&mut self);
+    fn save_state(&self) -> GuidanceState;
+    fn restore_state(&mut self, state: &GuidanceState) -> Result<(), GuidanceError>;
+    // yaw and body-fixed linear velocity (surge, sway) of the vessel
+    fn set_velocity(&mut self, _yaw: f64, _vel: &Vector2<f64>) {}
+    // signed curvature of the path at the projection point
+    fn set_curvature(&mut self, _curvature: f64) {}
+    fn set_yaw_rate_mode(&mut self, _mode: YawRateMode) {}
+    // what the yaw reference is, a course unless the law compensates the sideslip itself
+    fn get_output_mode(&self) -> OutputMode {
+        OutputMode::Course
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elos::ELOS;
+    use crate::ilos::ILOS;
+
+    #[test]
+    fn try_update_rejects_invalid_inputs() {
+        let mut ilos = ILOS::new(0.5, 0.1);
+        let (pos, pos_d, tau) = (Vector2::new(0.0, 1.0), Vector2::zeros(), Vector2::x_axis());
+        let state = ilos.save_state();
+
+        assert_eq!(
+            ilos.try_update(&pos, &pos_d, &tau, 0.0),
+            Err(GuidanceError::InvalidTimeStep(0.0))
+        );
+        assert_eq!(
+            ilos.try_update(&Vector2::new(f64::NAN, 0.0), &pos_d, &tau, 0.1),
+            Err(GuidanceError::NonFiniteInput)
+        );
+        assert_eq!(
+            ilos.set_gains(0.0, 0.1),
+            Err(GuidanceError::InvalidGains(0.0, 0.1))
+        );
+        assert_eq!(ilos.save_state(), state);
+    }
+
+    #[test]
+    fn try_update_restores_the_state_on_non_finite_output() {
+        let mut ilos = ILOS::new(0.5, 0.1);
+        let (pos, pos_d, tau) = (Vector2::new(0.0, 1.0), Vector2::zeros(), Vector2::x_axis());
+        ilos.try_update(&pos, &pos_d, &tau, 0.1).unwrap();
+        let state = ilos.save_state();
+
+        ilos.set_yaw_rate_mode(YawRateMode::Analytic);
+        ilos.set_velocity(0.0, &Vector2::new(f64::NAN, 0.0));
+        assert_eq!(
+            ilos.try_update(&pos, &pos_d, &tau, 0.1),
+            Err(GuidanceError::NonFiniteOutput)
+        );
+        assert_eq!(ilos.save_state(), state);
+    }
+
+    #[test]
+    fn try_update_rejects_a_diverged_estimate() {
+        let mut elos = ELOS::default();
+        let (pos, pos_d, tau) = (Vector2::new(0.0, 1.0), Vector2::zeros(), Vector2::x_axis());
+        elos.set_velocity(0.0, &Vector2::new(1.0, 0.0));
+        elos.try_update(&pos, &pos_d, &tau, 0.1).unwrap();
+        let state = elos.save_state();
+
+        elos.set_velocity(0.0, &Vector2::new(f64::NAN, 0.0));
+        assert_eq!(
+            elos.try_update(&pos, &pos_d, &tau, 0.1),
+            Err(GuidanceError::NonFiniteOutput)
+        );
+        assert_eq!(elos.save_state(), state);
+
+        elos.set_velocity(0.0, &Vector2::new(1.0, 0.0));
+        elos.try_update(&pos, &pos_d, &tau, 0.1).unwrap();
+        assert!(elos.get_references().0.is_finite());
+    }
+}
@@ -2,7 +2,31 @@ extern crate nalgebra as na;
 
 use na::{Matrix2, UnitVector2, Vector2};
 
+use crate::guidance_law::{
+    body_to_ned, comp_path_rates, validate_gains, GuidanceError, GuidanceLaw, GuidanceState,
+    YawRateMode,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AntiWindup {
+    // saturation of the integral state
+    pub integral_limit: Option<f64>,
+    // the integral state is frozen while the cross-track error is above this threshold
+    pub integration_threshold: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ILOSState {
+    pub yaw_angle: f64,
+    pub yaw_rate: f64,
+    pub integral_state: f64,
+    pub kp: f64,
+    pub ki: f64,
+}
+
 #[allow(non_snake_case)]
+#[derive(Clone)]
 pub struct ILOS {
     yaw_angle: f64,
     yaw_rate: f64,
@@ -10,6 +34,10 @@ pub struct ILOS {
     S: Matrix2<f64>,
     kp: f64,
     ki: f64,
+    anti_windup: AntiWindup,
+    yaw_rate_mode: YawRateMode,
+    curvature: f64,
+    vel: Vector2<f64>,
 }
 
 impl Default for ILOS {
@@ -27,10 +55,40 @@ impl ILOS {
             S: Matrix2::new(0.0, -1.0, 1.0, 0.0),
             kp: prop_gain,
             ki: integral_gain,
+            anti_windup: AntiWindup::default(),
+            yaw_rate_mode: YawRateMode::FiniteDifference,
+            curvature: 0.0,
+            vel: Vector2::zeros(),
         }
     }
 
-    pub fn update(
+    // the limit and the threshold have to be positive and finite
+    pub fn set_anti_windup(&mut self, anti_windup: AntiWindup) -> Result<(), GuidanceError> {
+        let valid = |value: Option<f64>| match value {
+            Some(value) => value.is_finite() && value > 0.0,
+            None => true,
+        };
+        if !(valid(anti_windup.integral_limit) && valid(anti_windup.integration_threshold)) {
+            return Err(GuidanceError::InvalidAntiWindup(anti_windup));
+        }
+        self.anti_windup = anti_windup;
+        if let Some(limit) = anti_windup.integral_limit {
+            self.integral_state = self.integral_state.clamp(-limit, limit);
+        }
+        Ok(())
+    }
+
+    pub fn get_anti_windup(&self) -> AntiWindup {
+        self.anti_windup
+    }
+
+    pub fn get_integral_state(&self) -> f64 {
+        self.integral_state
+    }
+}
+
+impl GuidanceLaw for ILOS {
+    fn update(
         &mut self,
         pos: &Vector2<f64>,
         pos_d: &Vector2<f64>,
@@ -45,26 +103,89 @@ impl ILOS {
             - (k * cross_track_err + c * self.integral_state) * self.S * tau.into_inner())
             / (1.0 + (k * cross_track_err + c * self.integral_state).powi(2)).sqrt();
 
+        let integrate = match self.anti_windup.integration_threshold {
+            Some(threshold) => cross_track_err.abs() <= threshold,
+            None => true,
+        };
+        let alpha_dot = if integrate {
+            k * cross_track_err
+                / (1.0 + (k * cross_track_err + c * self.integral_state).powi(2)).sqrt()
+        } else {
+            0.0
+        };
+
         let yaw_angle_prev = self.yaw_angle;
         self.yaw_angle = mu[1].atan2(mu[0]);
-        self.yaw_rate = ssa(ssa(self.yaw_angle) - ssa(yaw_angle_prev)) / dt;
+        self.yaw_rate = match self.yaw_rate_mode {
+            YawRateMode::FiniteDifference => ssa(ssa(self.yaw_angle) - ssa(yaw_angle_prev)) / dt,
+            YawRateMode::Analytic => {
+                let (path_angle_rate, cross_track_err_rate) =
+                    comp_path_rates(tau, cross_track_err, self.curvature, &self.vel);
+                path_angle_rate
+                    - (k * cross_track_err_rate + c * alpha_dot)
+                        / (1.0 + (k * cross_track_err + c * self.integral_state).powi(2))
+            }
+        };
 
-        let alpha_dot = k * cross_track_err
-            / (1.0 + (k * cross_track_err + c * self.integral_state).powi(2)).sqrt();
         self.integral_state += alpha_dot * dt;
+        if let Some(limit) = self.anti_windup.integral_limit {
+            self.integral_state = self.integral_state.clamp(-limit, limit);
+        }
+    }
+
+    fn get_references(&self) -> (f64, f64) {
+        (self.yaw_angle, self.yaw_rate)
     }
 
-    pub fn set_gains(&mut self, prop_gain: f64, integral_gain: f64) {
+    fn set_gains(&mut self, prop_gain: f64, integral_gain: f64) -> Result<(), GuidanceError> {
+        validate_gains(prop_gain, integral_gain)?;
         self.kp = prop_gain;
         self.ki = integral_gain;
+        Ok(())
     }
 
-    pub fn get_gains(&self) -> (f64, f64) {
+    fn get_gains(&self) -> (f64, f64) {
         (self.kp, self.ki)
     }
 
-    pub fn get_references(&self) -> (f64, f64) {
-        (self.yaw_angle, self.yaw_rate)
+    fn reset(&mut self) {
+        self.integral_state = 0.0;
+    }
+
+    fn save_state(&self) -> GuidanceState {
+        GuidanceState::ILOS(ILOSState {
+            yaw_angle: self.yaw_angle,
+            yaw_rate: self.yaw_rate,
+            integral_state: self.integral_state,
+            kp: self.kp,
+            ki: self.ki,
+        })
+    }
+
+    fn restore_state(&mut self, state: &GuidanceState) -> Result<(), GuidanceError> {
+        match state {
+            GuidanceState::ILOS(state) => {
+                self.yaw_angle = state.yaw_angle;
+                self.yaw_rate = state.yaw_rate;
+                self.integral_state = state.integral_state;
+                self.kp = state.kp;
+                self.ki = state.ki;
+                Ok(())
+            }
+            _ => Err(GuidanceError::StateMismatch),
+        }
+    }
+
+    fn set_velocity(&mut self, yaw: f64, vel: &Vector2<f64>) {
+        self.vel = body_to_ned(yaw, vel);
+    }
+
+    fn set_curvature(&mut self, curvature: f64) {
+        self.curvature = curvature;
+    }
+
+    fn set_yaw_rate_mode(&mut self, mode: YawRateMode) {
+        self.yaw_rate_mode = mode;
     }
 }
 
@@ -76,3 +197,44 @@ pub fn ssa(ang: f64) -> f64 {
 fn modulo(m: f64, n: f64) -> f64 {
     (m % n + n) % n
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_anti_windup_settings_are_rejected() {
+        let mut ilos = ILOS::new(0.5, 0.1);
+        for limit in [-1.0, 0.0, f64::NAN, f64::INFINITY] {
+            let anti_windup = AntiWindup {
+                integral_limit: Some(limit),
+                integration_threshold: None,
+            };
+            assert!(ilos.set_anti_windup(anti_windup).is_err());
+        }
+        let anti_windup = AntiWindup {
+            integral_limit: None,
+            integration_threshold: Some(-2.0),
+        };
+        assert_eq!(
+            ilos.set_anti_windup(anti_windup),
+            Err(GuidanceError::InvalidAntiWindup(anti_windup))
+        );
+        assert_eq!(ilos.get_anti_windup(), AntiWindup::default());
+    }
+
+    #[test]
+    fn integral_state_is_saturated() {
+        let mut ilos = ILOS::new(0.5, 0.1);
+        ilos.set_anti_windup(AntiWindup {
+            integral_limit: Some(0.5),
+            integration_threshold: None,
+        })
+        .unwrap();
+        let (pos, pos_d, tau) = (Vector2::new(0.0, 10.0), Vector2::zeros(), Vector2::x_axis());
+        for _ in 0..100 {
+            ilos.try_update(&pos, &pos_d, &tau, 0.1).unwrap();
+        }
+        assert_eq!(ilos.get_integral_state().abs(), 0.5);
+    }
+}
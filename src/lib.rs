@@ -1,9 +1,22 @@
+pub mod alos;
+pub mod elos;
+pub mod gain_schedule;
+pub mod guidance_law;
 pub mod ilos;
+pub mod los;
+pub mod reference_filter;
+pub mod speed_assignment;
 pub mod paths {
     pub mod circle;
     pub mod waypoint_path;
     pub mod path;
     pub mod line;
+    pub mod spline;
+    pub mod spiral;
+    pub mod dubins;
+    pub mod composite;
+    pub mod survey;
+    pub mod search;
 }
 
 #[cfg(feature = "zenoh")]
@@ -0,0 +1,300 @@
+extern crate nalgebra as na;
+
+use na::{Matrix2, UnitVector2, Vector2};
+
+use crate::guidance_law::{
+    body_to_ned, comp_path_rates, validate_gains, GuidanceError, GuidanceLaw, GuidanceState,
+    YawRateMode,
+};
+use crate::ilos::ssa;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Lookahead {
+    Constant(f64),
+    // Delta(e) = (delta_max - delta_min) * exp(-gamma * e^2) + delta_min
+    TimeVarying {
+        delta_min: f64,
+        delta_max: f64,
+        gamma: f64,
+    },
+}
+
+impl Lookahead {
+    pub fn try_constant(delta: f64) -> Result<Lookahead, GuidanceError> {
+        let lookahead = Lookahead::Constant(delta);
+        lookahead.validate()?;
+        Ok(lookahead)
+    }
+
+    pub fn try_time_varying(
+        delta_min: f64,
+        delta_max: f64,
+        gamma: f64,
+    ) -> Result<Lookahead, GuidanceError> {
+        let lookahead = Lookahead::TimeVarying {
+            delta_min,
+            delta_max,
+            gamma,
+        };
+        lookahead.validate()?;
+        Ok(lookahead)
+    }
+
+    // The distances have to be positive with delta_min <= delta_max and gamma non-negative, a
+    // zero lookahead divides by zero on the path and a negative one steers away from it
+    pub fn validate(&self) -> Result<(), GuidanceError> {
+        let valid = match *self {
+            Lookahead::Constant(delta) => delta.is_finite() && delta > 0.0,
+            Lookahead::TimeVarying {
+                delta_min,
+                delta_max,
+                gamma,
+            } => {
+                delta_min.is_finite()
+                    && delta_min > 0.0
+                    && delta_max.is_finite()
+                    && delta_max >= delta_min
+                    && gamma.is_finite()
+                    && gamma >= 0.0
+            }
+        };
+        if !valid {
+            return Err(GuidanceError::InvalidLookahead(*self));
+        }
+        Ok(())
+    }
+
+    pub fn comp_lookahead(&self, cross_track_err: f64) -> f64 {
+        match *self {
+            Lookahead::Constant(delta) => delta,
+            Lookahead::TimeVarying {
+                delta_min,
+                delta_max,
+                gamma,
+            } => (delta_max - delta_min) * (-gamma * cross_track_err.powi(2)).exp() + delta_min,
+        }
+    }
+
+    // lookahead distance on the path, where the cross-track error is zero
+    pub fn get_nominal(&self) -> f64 {
+        self.comp_lookahead(0.0)
+    }
+
+    // Sets the lookahead distance on the path, a time-varying lookahead keeps delta_min and gamma.
+    // Leaves the lookahead untouched if the result is invalid.
+    pub fn set_nominal(&mut self, delta: f64) -> Result<(), GuidanceError> {
+        let mut lookahead = *self;
+        match &mut lookahead {
+            Lookahead::Constant(lookahead) => *lookahead = delta,
+            Lookahead::TimeVarying { delta_max, .. } => *delta_max = delta,
+        }
+        lookahead.validate()?;
+        *self = lookahead;
+        Ok(())
+    }
+
+    // d/de atan(e / Delta(e))
+    pub fn comp_los_angle_derivative(&self, cross_track_err: f64) -> f64 {
+        let delta = self.comp_lookahead(cross_track_err);
+        let delta_derivative = match *self {
+            Lookahead::Constant(_) => 0.0,
+            Lookahead::TimeVarying {
+                delta_min,
+                delta_max,
+                gamma,
+            } => {
+                -2.0 * gamma
+                    * cross_track_err
+                    * (delta_max - delta_min)
+                    * (-gamma * cross_track_err.powi(2)).exp()
+            }
+        };
+        (delta - cross_track_err * delta_derivative) / (delta.powi(2) + cross_track_err.powi(2))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LOSState {
+    pub yaw_angle: f64,
+    pub yaw_rate: f64,
+    pub lookahead: Lookahead,
+}
+
+#[allow(non_snake_case)]
+#[derive(Clone)]
+pub struct LOS {
+    yaw_angle: f64,
+    yaw_rate: f64,
+    S: Matrix2<f64>,
+    lookahead: Lookahead,
+    yaw_rate_mode: YawRateMode,
+    curvature: f64,
+    vel: Vector2<f64>,
+}
+
+impl Default for LOS {
+    fn default() -> Self {
+        Self::new(Lookahead::Constant(1.0))
+    }
+}
+
+impl LOS {
+    pub fn new(lookahead: Lookahead) -> LOS {
+        LOS {
+            yaw_angle: 0.0,
+            yaw_rate: 0.0,
+            S: Matrix2::new(0.0, -1.0, 1.0, 0.0),
+            lookahead,
+            yaw_rate_mode: YawRateMode::FiniteDifference,
+            curvature: 0.0,
+            vel: Vector2::zeros(),
+        }
+    }
+
+    pub fn try_new(lookahead: Lookahead) -> Result<LOS, GuidanceError> {
+        lookahead.validate()?;
+        Ok(Self::new(lookahead))
+    }
+
+    pub fn set_lookahead(&mut self, lookahead: Lookahead) -> Result<(), GuidanceError> {
+        lookahead.validate()?;
+        self.lookahead = lookahead;
+        Ok(())
+    }
+
+    pub fn get_lookahead(&self) -> Lookahead {
+        self.lookahead
+    }
+}
+
+impl GuidanceLaw for LOS {
+    fn update(
+        &mut self,
+        pos: &Vector2<f64>,
+        pos_d: &Vector2<f64>,
+        tau: &UnitVector2<f64>,
+        dt: f64,
+    ) {
+        let cross_track_err = (pos - pos_d).dot(&(self.S * tau.into_inner()));
+        let delta = self.lookahead.comp_lookahead(cross_track_err);
+
+        let mu = (tau.into_inner() - cross_track_err / delta * self.S * tau.into_inner())
+            / (1.0 + (cross_track_err / delta).powi(2)).sqrt();
+
+        let yaw_angle_prev = self.yaw_angle;
+        self.yaw_angle = mu[1].atan2(mu[0]);
+        self.yaw_rate = match self.yaw_rate_mode {
+            YawRateMode::FiniteDifference => ssa(ssa(self.yaw_angle) - ssa(yaw_angle_prev)) / dt,
+            YawRateMode::Analytic => {
+                let (path_angle_rate, cross_track_err_rate) =
+                    comp_path_rates(tau, cross_track_err, self.curvature, &self.vel);
+                path_angle_rate
+                    - self.lookahead.comp_los_angle_derivative(cross_track_err)
+                        * cross_track_err_rate
+            }
+        };
+    }
+
+    fn get_references(&self) -> (f64, f64) {
+        (self.yaw_angle, self.yaw_rate)
+    }
+
+    fn set_gains(&mut self, prop_gain: f64, integral_gain: f64) -> Result<(), GuidanceError> {
+        validate_gains(prop_gain, integral_gain)?;
+        self.lookahead.set_nominal(1.0 / prop_gain)?;
+        Ok(())
+    }
+
+    fn get_gains(&self) -> (f64, f64) {
+        (1.0 / self.lookahead.get_nominal(), 0.0)
+    }
+
+    fn reset(&mut self) {}
+
+    fn save_state(&self) -> GuidanceState {
+        GuidanceState::LOS(LOSState {
+            yaw_angle: self.yaw_angle,
+            yaw_rate: self.yaw_rate,
+            lookahead: self.lookahead,
+        })
+    }
+
+    fn restore_state(&mut self, state: &GuidanceState) -> Result<(), GuidanceError> {
+        match state {
+            GuidanceState::LOS(state) => {
+                self.yaw_angle = state.yaw_angle;
+                self.yaw_rate = state.yaw_rate;
+                self.lookahead = state.lookahead;
+                Ok(())
+            }
+            _ => Err(GuidanceError::StateMismatch),
+        }
+    }
+
+    fn set_velocity(&mut self, yaw: f64, vel: &Vector2<f64>) {
+        self.vel = body_to_ned(yaw, vel);
+    }
+
+    fn set_curvature(&mut self, curvature: f64) {
+        self.curvature = curvature;
+    }
+
+    fn set_yaw_rate_mode(&mut self, mode: YawRateMode) {
+        self.yaw_rate_mode = mode;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_lookaheads_are_rejected() {
+        assert!(Lookahead::try_constant(0.0).is_err());
+        assert!(Lookahead::try_constant(-5.0).is_err());
+        assert!(Lookahead::try_time_varying(0.0, 10.0, 0.01).is_err());
+        assert!(Lookahead::try_time_varying(10.0, 5.0, 0.01).is_err());
+        assert!(Lookahead::try_time_varying(2.0, 10.0, -0.01).is_err());
+        assert!(Lookahead::try_time_varying(2.0, 10.0, f64::NAN).is_err());
+        assert!(LOS::try_new(Lookahead::Constant(0.0)).is_err());
+
+        let lookahead = Lookahead::try_time_varying(2.0, 10.0, 0.01).unwrap();
+        assert_eq!(lookahead.comp_lookahead(0.0), 10.0);
+        assert!((lookahead.comp_lookahead(1e3) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn set_gains_keeps_the_lookahead_above_delta_min() {
+        let lookahead = Lookahead::try_time_varying(2.0, 10.0, 0.01).unwrap();
+        let mut los = LOS::try_new(lookahead).unwrap();
+
+        // nominal lookahead of 1 below delta_min
+        assert_eq!(
+            los.set_gains(1.0, 0.0),
+            Err(GuidanceError::InvalidLookahead(Lookahead::TimeVarying {
+                delta_min: 2.0,
+                delta_max: 1.0,
+                gamma: 0.01,
+            }))
+        );
+        assert_eq!(los.get_lookahead(), lookahead);
+
+        los.set_gains(0.25, 0.0).unwrap();
+        assert_eq!(los.get_lookahead().get_nominal(), 4.0);
+    }
+
+    #[test]
+    fn yaw_is_finite_on_the_path() {
+        let mut los = LOS::try_new(Lookahead::try_constant(5.0).unwrap()).unwrap();
+        los.try_update(
+            &Vector2::zeros(),
+            &Vector2::zeros(),
+            &Vector2::x_axis(),
+            0.1,
+        )
+        .unwrap();
+        assert_eq!(los.get_references().0, 0.0);
+    }
+}
@@ -2,7 +2,7 @@ extern crate nalgebra as na;
 
 use na::{UnitVector2, Vector2};
 
-use crate::paths::path::Path;
+use crate::paths::path::{Path, PathSegment};
 
 pub struct Circle {
     radius: f64,
@@ -15,6 +15,10 @@ impl Circle {
         let q = if clockwise { -1.0 } else { 1.0 };
         Circle { radius, center, q }
     }
+
+    pub fn get_radius(&self) -> f64 {
+        self.radius
+    }
 }
 
 impl Path for Circle {
@@ -31,4 +35,26 @@ impl Path for Circle {
         let tangent = Vector2::new(-theta.sin(), self.q * theta.cos());
         UnitVector2::new_normalize(tangent)
     }
+
+    #[allow(unused_variables)]
+    fn comp_curvature(&self, theta: f64) -> f64 {
+        self.q / self.radius
+    }
+
+    #[allow(unused_variables)]
+    fn comp_arc_length_derivative(&self, theta: f64) -> f64 {
+        self.radius
+    }
+
+    fn theta_range(&self) -> (f64, f64) {
+        (-core::f64::consts::PI, core::f64::consts::PI)
+    }
+
+    fn total_length(&self) -> f64 {
+        2.0 * core::f64::consts::PI * self.radius
+    }
+
+    fn current_segment_type(&self) -> PathSegment {
+        PathSegment::Circle
+    }
 }
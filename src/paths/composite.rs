@@ -0,0 +1,216 @@
+extern crate nalgebra as na;
+
+use na::{UnitVector2, Vector2};
+
+use crate::ilos::ssa;
+use crate::paths::circle::Circle;
+use crate::paths::line::Line;
+use crate::paths::path::{Path, PathSegment};
+
+// Sequence of segments tracked one after the other, each within its theta bounds. The reference
+// stops at the end of the last segment once it has been passed.
+pub struct CompositePath {
+    segments: Vec<Box<dyn Path + Send>>,
+    theta_min: Vec<f64>,
+    theta_max: Vec<f64>,
+    segment_lengths: Vec<f64>,
+    current_segment: usize,
+    theta: f64,
+    completed: bool,
+    end_tangent: UnitVector2<f64>,
+}
+
+impl Default for CompositePath {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CompositePath {
+    // segments have to be added before the path is tracked
+    pub fn new() -> CompositePath {
+        CompositePath {
+            segments: Vec::new(),
+            theta_min: Vec::new(),
+            theta_max: Vec::new(),
+            segment_lengths: Vec::new(),
+            current_segment: 0,
+            theta: 0.0,
+            completed: false,
+            end_tangent: Vector2::x_axis(),
+        }
+    }
+
+    // segment with a constant arc length derivative, tracked from theta_min to theta_max
+    pub fn push_segment(&mut self, segment: Box<dyn Path + Send>, theta_min: f64, theta_max: f64) {
+        if self.segments.is_empty() {
+            self.theta = theta_min;
+        }
+        self.segment_lengths
+            .push((theta_max - theta_min) * segment.comp_arc_length_derivative(theta_min));
+        self.end_tangent = segment.comp_tangent(theta_max);
+        self.segments.push(segment);
+        self.theta_min.push(theta_min);
+        self.theta_max.push(theta_max);
+    }
+
+    pub fn push_line(&mut self, start: Vector2<f64>, end: Vector2<f64>) {
+        let line = Line::new(start, end);
+        let length = line.total_length();
+        self.push_segment(Box::new(line), 0.0, length);
+    }
+
+    // Arc from start around center, counter-clockwise for positive angles. Returns the end point.
+    pub fn push_arc(
+        &mut self,
+        start: Vector2<f64>,
+        center: Vector2<f64>,
+        angle: f64,
+    ) -> Vector2<f64> {
+        let mut circle = Circle::new((start - center).norm(), center, angle < 0.0);
+        let theta_min = circle.comp_theta(&start);
+        let theta_max = theta_min + angle.abs();
+        let end = circle.comp_pos(theta_max);
+        self.push_segment(Box::new(circle), theta_min, theta_max);
+        end
+    }
+
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn get_segment_lengths(&self) -> &[f64] {
+        &self.segment_lengths
+    }
+
+    fn comp_segment_theta(&mut self, i: usize, pos: &Vector2<f64>) -> f64 {
+        let theta = self.segments[i].comp_theta(pos);
+        match self.segments[i].current_segment_type() {
+            // unwrapped around the previous theta, arcs may be longer than pi
+            PathSegment::Circle => self.theta + ssa(theta - self.theta),
+            PathSegment::Line => theta,
+        }
+    }
+}
+
+impl Path for CompositePath {
+    fn comp_theta(&mut self, pos: &Vector2<f64>) -> f64 {
+        let n = self.segments.len();
+        if self.completed {
+            return self.theta_max[n - 1];
+        }
+
+        for _ in 0..n {
+            let i = self.current_segment;
+            let theta = self.comp_segment_theta(i, pos);
+            if theta > self.theta_max[i] {
+                if i == n - 1 {
+                    self.completed = true;
+                    self.theta = self.theta_max[i];
+                    return self.theta;
+                }
+                self.current_segment += 1;
+                self.theta = self.theta_min[i + 1];
+            } else if theta < self.theta_min[i] && i > 0 {
+                self.current_segment -= 1;
+                self.theta = self.theta_max[i - 1];
+            } else {
+                self.theta = theta;
+                break;
+            }
+        }
+        self.theta
+    }
+
+    fn comp_pos(&self, theta: f64) -> Vector2<f64> {
+        self.segments[self.current_segment].comp_pos(theta)
+    }
+
+    fn comp_tangent(&self, theta: f64) -> UnitVector2<f64> {
+        if self.completed {
+            return self.end_tangent;
+        }
+        self.segments[self.current_segment].comp_tangent(theta)
+    }
+
+    fn comp_curvature(&self, theta: f64) -> f64 {
+        self.segments[self.current_segment].comp_curvature(theta)
+    }
+
+    fn comp_max_curvature_ahead(&self, theta: f64, distance: f64) -> f64 {
+        let i = self.current_segment;
+        let mut curvature = self.segments[i].comp_max_curvature_ahead(theta, distance);
+
+        // distance left beyond the end of each segment, short segments may be passed entirely
+        let mut remaining = distance
+            - (self.theta_max[i] - theta) * self.segments[i].comp_arc_length_derivative(theta);
+        for j in i + 1..self.segments.len() {
+            if remaining <= 0.0 {
+                break;
+            }
+            curvature = curvature
+                .max(self.segments[j].comp_max_curvature_ahead(self.theta_min[j], remaining));
+            remaining -= self.segment_lengths[j];
+        }
+        curvature
+    }
+
+    fn comp_arc_length_derivative(&self, theta: f64) -> f64 {
+        self.segments[self.current_segment].comp_arc_length_derivative(theta)
+    }
+
+    fn theta_range(&self) -> (f64, f64) {
+        (
+            self.theta_min[self.current_segment],
+            self.theta_max[self.current_segment],
+        )
+    }
+
+    fn total_length(&self) -> f64 {
+        self.segment_lengths.iter().sum()
+    }
+
+    fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    fn comp_progress(&self, theta: f64) -> f64 {
+        if self.completed {
+            return 1.0;
+        }
+        let i = self.current_segment;
+        let travelled = self.segment_lengths[..i].iter().sum::<f64>()
+            + (theta - self.theta_min[i]) * self.comp_arc_length_derivative(theta);
+        (travelled / self.total_length()).clamp(0.0, 1.0)
+    }
+
+    fn current_segment(&self) -> usize {
+        self.current_segment
+    }
+
+    fn current_segment_type(&self) -> PathSegment {
+        self.segments[self.current_segment].current_segment_type()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn max_curvature_ahead_looks_past_short_segments() {
+        // a long line, a short line and a tight arc
+        let mut path = CompositePath::new();
+        path.push_line(Vector2::new(0.0, 0.0), Vector2::new(10.0, 0.0));
+        path.push_line(Vector2::new(10.0, 0.0), Vector2::new(12.0, 0.0));
+        path.push_arc(Vector2::new(12.0, 0.0), Vector2::new(12.0, 2.0), PI);
+
+        assert_eq!(path.comp_max_curvature_ahead(5.0, 6.0), 0.0);
+        assert!((path.comp_max_curvature_ahead(5.0, 8.0) - 0.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,364 @@
+extern crate nalgebra as na;
+
+use na::{UnitVector2, Vector2};
+
+use crate::paths::composite::CompositePath;
+use crate::paths::line::Line;
+use crate::paths::path::{Path, PathSegment};
+
+use std::f64::consts::PI;
+use std::fmt;
+
+// L turns counter-clockwise (positive curvature), R clockwise and S goes straight
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DubinsWord {
+    LSL,
+    RSR,
+    LSR,
+    RSL,
+    RLR,
+    LRL,
+}
+
+impl DubinsWord {
+    pub const ALL: [DubinsWord; 6] = [
+        DubinsWord::LSL,
+        DubinsWord::RSR,
+        DubinsWord::LSR,
+        DubinsWord::RSL,
+        DubinsWord::RLR,
+        DubinsWord::LRL,
+    ];
+
+    // turn direction of each segment, 1 for L, -1 for R and 0 for S
+    fn directions(&self) -> [f64; 3] {
+        match self {
+            DubinsWord::LSL => [1.0, 0.0, 1.0],
+            DubinsWord::RSR => [-1.0, 0.0, -1.0],
+            DubinsWord::LSR => [1.0, 0.0, -1.0],
+            DubinsWord::RSL => [-1.0, 0.0, 1.0],
+            DubinsWord::RLR => [-1.0, 1.0, -1.0],
+            DubinsWord::LRL => [1.0, -1.0, 1.0],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DubinsError {
+    InvalidTurnRadius(f64),
+    NonFinitePose,
+}
+
+impl fmt::Display for DubinsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DubinsError::InvalidTurnRadius(radius) => write!(f, "invalid turn radius: {}", radius),
+            DubinsError::NonFinitePose => write!(f, "start or goal pose is not finite"),
+        }
+    }
+}
+
+impl std::error::Error for DubinsError {}
+
+fn validate(
+    start: &Vector2<f64>,
+    start_heading: f64,
+    goal: &Vector2<f64>,
+    goal_heading: f64,
+    turn_radius: f64,
+) -> Result<(), DubinsError> {
+    if !(turn_radius.is_finite() && turn_radius > 0.0) {
+        return Err(DubinsError::InvalidTurnRadius(turn_radius));
+    }
+    let finite = start.iter().chain(goal.iter()).all(|x| x.is_finite())
+        && start_heading.is_finite()
+        && goal_heading.is_finite();
+    if !finite {
+        return Err(DubinsError::NonFinitePose);
+    }
+    Ok(())
+}
+
+fn mod2pi(angle: f64) -> f64 {
+    angle.rem_euclid(2.0 * PI)
+}
+
+// Segment parameters of the word for a unit turn radius, with the goal at distance d along the
+// x-axis and alpha and beta the start and goal headings. Turns are given as angles and the
+// straight segment as its length. None if the word cannot connect the poses.
+fn comp_word_parameters(word: DubinsWord, alpha: f64, beta: f64, d: f64) -> Option<[f64; 3]> {
+    let (sa, ca) = alpha.sin_cos();
+    let (sb, cb) = beta.sin_cos();
+    let cab = (alpha - beta).cos();
+    match word {
+        DubinsWord::LSL => {
+            let p_squared = 2.0 + d.powi(2) - 2.0 * cab + 2.0 * d * (sa - sb);
+            let tmp = (cb - ca).atan2(d + sa - sb);
+            Some([
+                mod2pi(tmp - alpha),
+                p_squared.max(0.0).sqrt(),
+                mod2pi(beta - tmp),
+            ])
+        }
+        DubinsWord::RSR => {
+            let p_squared = 2.0 + d.powi(2) - 2.0 * cab + 2.0 * d * (sb - sa);
+            let tmp = (ca - cb).atan2(d - sa + sb);
+            Some([
+                mod2pi(alpha - tmp),
+                p_squared.max(0.0).sqrt(),
+                mod2pi(tmp - beta),
+            ])
+        }
+        DubinsWord::LSR => {
+            let p_squared = -2.0 + d.powi(2) + 2.0 * cab + 2.0 * d * (sa + sb);
+            if p_squared < 0.0 {
+                return None;
+            }
+            let p = p_squared.sqrt();
+            let tmp = (-ca - cb).atan2(d + sa + sb) - (-2.0_f64).atan2(p);
+            Some([mod2pi(tmp - alpha), p, mod2pi(tmp - beta)])
+        }
+        DubinsWord::RSL => {
+            let p_squared = -2.0 + d.powi(2) + 2.0 * cab - 2.0 * d * (sa + sb);
+            if p_squared < 0.0 {
+                return None;
+            }
+            let p = p_squared.sqrt();
+            let tmp = (ca + cb).atan2(d - sa - sb) - 2.0_f64.atan2(p);
+            Some([mod2pi(alpha - tmp), p, mod2pi(beta - tmp)])
+        }
+        DubinsWord::RLR => {
+            let tmp = (6.0 - d.powi(2) + 2.0 * cab + 2.0 * d * (sa - sb)) / 8.0;
+            if tmp.abs() > 1.0 {
+                return None;
+            }
+            let p = mod2pi(2.0 * PI - tmp.acos());
+            let t = mod2pi(alpha - (ca - cb).atan2(d - sa + sb) + p / 2.0);
+            Some([t, p, mod2pi(alpha - beta - t + p)])
+        }
+        DubinsWord::LRL => {
+            let tmp = (6.0 - d.powi(2) + 2.0 * cab + 2.0 * d * (sb - sa)) / 8.0;
+            if tmp.abs() > 1.0 {
+                return None;
+            }
+            let p = mod2pi(2.0 * PI - tmp.acos());
+            let t = mod2pi(-alpha - (ca - cb).atan2(d + sa - sb) + p / 2.0);
+            Some([t, p, mod2pi(beta - alpha - t + p)])
+        }
+    }
+}
+
+// Shortest path between two poses with bounded curvature, made of circular arcs with the turn
+// radius and a straight line or a third arc. The reference stops at the goal pose once it has
+// been passed.
+pub struct DubinsPath {
+    word: DubinsWord,
+    turn_radius: f64,
+    path: CompositePath,
+}
+
+impl DubinsPath {
+    pub fn new(
+        start: Vector2<f64>,
+        start_heading: f64,
+        goal: Vector2<f64>,
+        goal_heading: f64,
+        turn_radius: f64,
+    ) -> DubinsPath {
+        Self::try_new(start, start_heading, goal, goal_heading, turn_radius)
+            .unwrap_or_else(|e| panic!("Invalid Dubins path: {}", e))
+    }
+
+    // the shortest of the six words, headings are angles from the x-axis towards the y-axis
+    pub fn try_new(
+        start: Vector2<f64>,
+        start_heading: f64,
+        goal: Vector2<f64>,
+        goal_heading: f64,
+        turn_radius: f64,
+    ) -> Result<DubinsPath, DubinsError> {
+        validate(&start, start_heading, &goal, goal_heading, turn_radius)?;
+        Ok(DubinsWord::ALL
+            .iter()
+            .filter_map(|&word| {
+                Self::with_word(word, start, start_heading, goal, goal_heading, turn_radius)
+            })
+            .min_by(|a, b| a.total_length().total_cmp(&b.total_length()))
+            .expect("LSL and RSR always connect two poses"))
+    }
+
+    // None if the word cannot connect the poses or the inputs are invalid
+    pub fn with_word(
+        word: DubinsWord,
+        start: Vector2<f64>,
+        start_heading: f64,
+        goal: Vector2<f64>,
+        goal_heading: f64,
+        turn_radius: f64,
+    ) -> Option<DubinsPath> {
+        validate(&start, start_heading, &goal, goal_heading, turn_radius).ok()?;
+        let diff = (goal - start) / turn_radius;
+        let phi = diff[1].atan2(diff[0]);
+        let parameters = comp_word_parameters(
+            word,
+            mod2pi(start_heading - phi),
+            mod2pi(goal_heading - phi),
+            diff.norm(),
+        )?;
+
+        let mut path = CompositePath::new();
+        let mut pos = start;
+        let mut heading = start_heading;
+        for (direction, parameter) in word.directions().into_iter().zip(parameters) {
+            let tangent = Vector2::new(heading.cos(), heading.sin());
+            if direction == 0.0 {
+                // built from the tangent so that a zero-length line keeps the heading
+                let length = parameter * turn_radius;
+                path.push_segment(Box::new(Line::new(pos, pos + tangent)), 0.0, length);
+                pos += length * tangent;
+            } else {
+                let center = pos + direction * turn_radius * Vector2::new(-tangent[1], tangent[0]);
+                pos = path.push_arc(pos, center, direction * parameter);
+                heading += direction * parameter;
+            }
+        }
+        Some(DubinsPath {
+            word,
+            turn_radius,
+            path,
+        })
+    }
+
+    pub fn get_word(&self) -> DubinsWord {
+        self.word
+    }
+
+    pub fn get_turn_radius(&self) -> f64 {
+        self.turn_radius
+    }
+
+    // lengths of the three segments
+    pub fn get_segment_lengths(&self) -> &[f64] {
+        self.path.get_segment_lengths()
+    }
+}
+
+impl Path for DubinsPath {
+    fn comp_theta(&mut self, pos: &Vector2<f64>) -> f64 {
+        self.path.comp_theta(pos)
+    }
+    fn comp_pos(&self, theta: f64) -> Vector2<f64> {
+        self.path.comp_pos(theta)
+    }
+    fn comp_tangent(&self, theta: f64) -> UnitVector2<f64> {
+        self.path.comp_tangent(theta)
+    }
+    fn comp_curvature(&self, theta: f64) -> f64 {
+        self.path.comp_curvature(theta)
+    }
+    fn comp_max_curvature_ahead(&self, theta: f64, distance: f64) -> f64 {
+        self.path.comp_max_curvature_ahead(theta, distance)
+    }
+    fn comp_arc_length_derivative(&self, theta: f64) -> f64 {
+        self.path.comp_arc_length_derivative(theta)
+    }
+    fn theta_range(&self) -> (f64, f64) {
+        self.path.theta_range()
+    }
+    fn total_length(&self) -> f64 {
+        self.path.total_length()
+    }
+    fn is_completed(&self) -> bool {
+        self.path.is_completed()
+    }
+    fn comp_progress(&self, theta: f64) -> f64 {
+        self.path.comp_progress(theta)
+    }
+    fn current_segment(&self) -> usize {
+        self.path.current_segment()
+    }
+    fn current_segment_type(&self) -> PathSegment {
+        self.path.current_segment_type()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // end pose after the segments, turns given as angles and the straight segment as its length
+    // for a unit turn radius
+    fn comp_end_pose(
+        word: DubinsWord,
+        mut pos: Vector2<f64>,
+        mut heading: f64,
+        parameters: [f64; 3],
+        turn_radius: f64,
+    ) -> (Vector2<f64>, f64) {
+        for (direction, parameter) in word.directions().into_iter().zip(parameters) {
+            let normal = Vector2::new(-heading.sin(), heading.cos());
+            if direction == 0.0 {
+                pos += parameter * turn_radius * Vector2::new(heading.cos(), heading.sin());
+            } else {
+                let center = pos + direction * turn_radius * normal;
+                heading += direction * parameter;
+                pos =
+                    center - direction * turn_radius * Vector2::new(-heading.sin(), heading.cos());
+            }
+        }
+        (pos, heading)
+    }
+
+    #[test]
+    fn words_recover_their_segments() {
+        let (start, start_heading, turn_radius) = (Vector2::new(3.0, -2.0), 0.4, 2.0);
+        for word in DubinsWord::ALL {
+            // the middle turn of the three-turn words is the longer of the two solutions
+            let parameters = match word {
+                DubinsWord::RLR | DubinsWord::LRL => [0.5, 4.0, 0.7],
+                _ => [0.5, 3.0, 1.0],
+            };
+            let (goal, goal_heading) =
+                comp_end_pose(word, start, start_heading, parameters, turn_radius);
+
+            let path =
+                DubinsPath::with_word(word, start, start_heading, goal, goal_heading, turn_radius)
+                    .unwrap();
+            for (length, parameter) in path.get_segment_lengths().iter().zip(parameters) {
+                assert!(
+                    (length - parameter * turn_radius).abs() < 1e-9,
+                    "{:?} {:?}",
+                    word,
+                    path.get_segment_lengths()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn shortest_path_to_a_pose_abeam() {
+        // half a turn to the left
+        let path =
+            DubinsPath::try_new(Vector2::zeros(), 0.0, Vector2::new(0.0, 4.0), PI, 2.0).unwrap();
+        assert!((path.total_length() - 2.0 * PI).abs() < 1e-9);
+
+        // straight ahead
+        let path =
+            DubinsPath::try_new(Vector2::zeros(), 0.0, Vector2::new(10.0, 0.0), 0.0, 2.0).unwrap();
+        assert!((path.total_length() - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn try_new_rejects_invalid_inputs() {
+        let (start, goal) = (Vector2::zeros(), Vector2::new(10.0, 0.0));
+        assert_eq!(
+            DubinsPath::try_new(start, 0.0, goal, 0.0, 0.0).err(),
+            Some(DubinsError::InvalidTurnRadius(0.0))
+        );
+        assert_eq!(
+            DubinsPath::try_new(start, f64::NAN, goal, 0.0, 1.0).err(),
+            Some(DubinsError::NonFinitePose)
+        );
+        assert!(DubinsPath::with_word(DubinsWord::LSL, start, 0.0, goal, 0.0, -1.0).is_none());
+    }
+}
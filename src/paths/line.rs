@@ -8,13 +8,19 @@ use crate::paths::path::Path;
 pub struct Line {
     angle: f64,
     pos_0: Vector2<f64>,
+    length: f64,
 }
 
 impl Line {
     pub fn new(pos_0: Vector2<f64>, pos_1: Vector2<f64>) -> Line {
         let angle = (pos_1[1] - pos_0[1]).atan2(pos_1[0] - pos_0[0]);
         // println!("angle: {}", angle);
-        Line { angle, pos_0 }
+        let length = (pos_1 - pos_0).norm();
+        Line {
+            angle,
+            pos_0,
+            length,
+        }
     }
 }
 
@@ -33,4 +39,17 @@ impl Path for Line {
         let tangent = Vector2::new(self.angle.cos(), self.angle.sin());
         UnitVector2::new_normalize(tangent)
     }
+
+    #[allow(unused_variables)]
+    fn comp_arc_length_derivative(&self, theta: f64) -> f64 {
+        1.0
+    }
+
+    fn theta_range(&self) -> (f64, f64) {
+        (0.0, self.length)
+    }
+
+    fn total_length(&self) -> f64 {
+        self.length
+    }
 }
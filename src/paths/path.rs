@@ -1,8 +1,201 @@
 extern crate nalgebra as na;
 use na::{UnitVector2, Vector2};
 
+const NEWTON_ITERATIONS: usize = 10;
+
+// 5-point Gauss-Legendre quadrature on [-1, 1]
+const GAUSS_NODES: [f64; 5] = [
+    -0.906_179_845_938_664,
+    -0.538_469_310_105_683,
+    0.0,
+    0.538_469_310_105_683,
+    0.906_179_845_938_664,
+];
+const GAUSS_WEIGHTS: [f64; 5] = [
+    0.236_926_885_056_189,
+    0.478_628_670_499_366,
+    0.568_888_888_888_889,
+    0.478_628_670_499_366,
+    0.236_926_885_056_189,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathSegment {
+    Line,
+    Circle,
+}
+
+// what a path does once the vessel passes its final point
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "zenoh", derive(clap::ValueEnum))]
+pub enum EndBehavior {
+    // freeze the reference at the final point and stop steering
+    #[default]
+    Stop,
+    // steer back towards the final point
+    Hold,
+    // start over from the first point
+    Loop,
+    // traverse the path backwards
+    Reverse,
+}
+
+// metadata of the leg from a waypoint to the next one
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct LegMetadata {
+    pub speed: Option<f64>,
+    pub acceptance_radius: Option<f64>,
+    pub tags: Vec<String>,
+}
+
+// arc length between theta_0 and theta_1 of a smooth curve with the given arc length derivative
+pub fn comp_arc_length<F: Fn(f64) -> f64>(
+    arc_length_derivative: F,
+    theta_0: f64,
+    theta_1: f64,
+) -> f64 {
+    let half = 0.5 * (theta_1 - theta_0);
+    let mid = 0.5 * (theta_1 + theta_0);
+    GAUSS_NODES
+        .iter()
+        .zip(GAUSS_WEIGHTS.iter())
+        .map(|(node, weight)| weight * half * arc_length_derivative(mid + half * node))
+        .sum()
+}
+
+// Closest point within [theta_min, theta_max] of a smooth curve, eval gives the position and its
+// first and second derivatives. Samples the range to find the basin of the closest point and
+// refines it with Newton iterations on (pos_d(theta) - pos) . pos_d'(theta) = 0.
+pub fn comp_closest_theta<F>(
+    eval: F,
+    pos: &Vector2<f64>,
+    theta_min: f64,
+    theta_max: f64,
+    n_samples: usize,
+) -> f64
+where
+    F: Fn(f64) -> (Vector2<f64>, Vector2<f64>, Vector2<f64>),
+{
+    let mut theta = theta_min;
+    let mut min_distance = f64::INFINITY;
+    for k in 0..=n_samples {
+        let theta_sample = theta_min + (theta_max - theta_min) * k as f64 / n_samples as f64;
+        let distance = (eval(theta_sample).0 - pos).norm_squared();
+        if distance < min_distance {
+            min_distance = distance;
+            theta = theta_sample;
+        }
+    }
+
+    for _ in 0..NEWTON_ITERATIONS {
+        let (pos_d, dpos_d, ddpos_d) = eval(theta);
+        let f = (pos_d - pos).dot(&dpos_d);
+        let df = dpos_d.norm_squared() + (pos_d - pos).dot(&ddpos_d);
+        if df <= 0.0 {
+            break;
+        }
+        theta = (theta - f / df).clamp(theta_min, theta_max);
+    }
+    theta
+}
+
 pub trait Path {
     fn comp_pos(&self, theta: f64) -> Vector2<f64>;
     fn comp_tangent(&self, theta: f64) -> UnitVector2<f64>;
     fn comp_theta(&mut self, pos: &Vector2<f64>) -> f64;
+    // signed curvature, positive for counter-clockwise turns
+    fn comp_curvature(&self, _theta: f64) -> f64 {
+        0.0
+    }
+    // largest absolute curvature within the given distance ahead of theta
+    fn comp_max_curvature_ahead(&self, theta: f64, _distance: f64) -> f64 {
+        self.comp_curvature(theta).abs()
+    }
+    // derivative of the arc length with respect to theta
+    fn comp_arc_length_derivative(&self, theta: f64) -> f64 {
+        let h = 1e-6;
+        (self.comp_pos(theta + h) - self.comp_pos(theta - h)).norm() / (2.0 * h)
+    }
+    // valid theta range of the current segment
+    fn theta_range(&self) -> (f64, f64) {
+        (-f64::INFINITY, f64::INFINITY)
+    }
+    fn total_length(&self) -> f64 {
+        f64::INFINITY
+    }
+    fn is_completed(&self) -> bool {
+        false
+    }
+    // what the path does once its end has been passed
+    fn get_end_behavior(&self) -> EndBehavior {
+        EndBehavior::Stop
+    }
+    // number of times a looping or reversing path has started over
+    fn completed_laps(&self) -> usize {
+        0
+    }
+    // metadata of the leg being tracked, for paths built from waypoints
+    fn current_leg(&self) -> Option<&LegMetadata> {
+        None
+    }
+    // travelled fraction of the total length
+    fn comp_progress(&self, _theta: f64) -> f64 {
+        0.0
+    }
+    fn current_segment(&self) -> usize {
+        0
+    }
+    fn current_segment_type(&self) -> PathSegment {
+        PathSegment::Line
+    }
+}
+
+impl<P: Path + ?Sized> Path for Box<P> {
+    fn comp_pos(&self, theta: f64) -> Vector2<f64> {
+        (**self).comp_pos(theta)
+    }
+    fn comp_tangent(&self, theta: f64) -> UnitVector2<f64> {
+        (**self).comp_tangent(theta)
+    }
+    fn comp_theta(&mut self, pos: &Vector2<f64>) -> f64 {
+        (**self).comp_theta(pos)
+    }
+    fn comp_curvature(&self, theta: f64) -> f64 {
+        (**self).comp_curvature(theta)
+    }
+    fn comp_max_curvature_ahead(&self, theta: f64, distance: f64) -> f64 {
+        (**self).comp_max_curvature_ahead(theta, distance)
+    }
+    fn comp_arc_length_derivative(&self, theta: f64) -> f64 {
+        (**self).comp_arc_length_derivative(theta)
+    }
+    fn theta_range(&self) -> (f64, f64) {
+        (**self).theta_range()
+    }
+    fn total_length(&self) -> f64 {
+        (**self).total_length()
+    }
+    fn is_completed(&self) -> bool {
+        (**self).is_completed()
+    }
+    fn get_end_behavior(&self) -> EndBehavior {
+        (**self).get_end_behavior()
+    }
+    fn completed_laps(&self) -> usize {
+        (**self).completed_laps()
+    }
+    fn current_leg(&self) -> Option<&LegMetadata> {
+        (**self).current_leg()
+    }
+    fn comp_progress(&self, theta: f64) -> f64 {
+        (**self).comp_progress(theta)
+    }
+    fn current_segment(&self) -> usize {
+        (**self).current_segment()
+    }
+    fn current_segment_type(&self) -> PathSegment {
+        (**self).current_segment_type()
+    }
 }
@@ -0,0 +1,139 @@
+extern crate nalgebra as na;
+
+use na::Vector2;
+
+use crate::paths::waypoint_path::{WaypointPath, WaypointPathError};
+
+use std::f64::consts::PI;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "zenoh", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SearchPatternType {
+    // legs of one, one, two, two, three, ... track spacings with 90 degree turns
+    ExpandingSquare,
+    // legs of one track spacing with 120 degree turns, returning through the datum every third
+    // leg so that the radial legs are 60 degrees apart
+    Sector,
+}
+
+// IAMSAR search pattern starting at the datum, with the first leg along the orientation angle
+// from the x-axis and all turns in the same direction
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchPattern {
+    pub pattern: SearchPatternType,
+    pub datum: Vector2<f64>,
+    pub track_spacing: f64,
+    pub n_legs: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub orientation: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub clockwise: bool,
+}
+
+impl SearchPattern {
+    pub fn new(
+        pattern: SearchPatternType,
+        datum: Vector2<f64>,
+        track_spacing: f64,
+        n_legs: usize,
+    ) -> SearchPattern {
+        SearchPattern {
+            pattern,
+            datum,
+            track_spacing,
+            n_legs,
+            orientation: 0.0,
+            clockwise: false,
+        }
+    }
+
+    // the datum followed by the end of each leg
+    pub fn comp_waypoints(&self) -> Vec<Vector2<f64>> {
+        let turn_angle = match self.pattern {
+            SearchPatternType::ExpandingSquare => 0.5 * PI,
+            SearchPatternType::Sector => 2.0 * PI / 3.0,
+        };
+        let turn_angle = if self.clockwise {
+            -turn_angle
+        } else {
+            turn_angle
+        };
+
+        let mut waypoints = vec![self.datum];
+        let mut pos = self.datum;
+        let mut heading = self.orientation;
+        for k in 0..self.n_legs {
+            let (length, turn) = match self.pattern {
+                SearchPatternType::ExpandingSquare => {
+                    ((k / 2 + 1) as f64 * self.track_spacing, true)
+                }
+                // straight on through the datum after the return leg
+                SearchPatternType::Sector => (self.track_spacing, k % 3 != 2),
+            };
+            pos += length * Vector2::new(heading.cos(), heading.sin());
+            if turn {
+                heading += turn_angle;
+            } else {
+                // back at the datum, without the rounding errors of the legs
+                pos = self.datum;
+            }
+            waypoints.push(pos);
+        }
+        waypoints
+    }
+
+    pub fn to_waypoint_path(&self, turn_radius: f64) -> Result<WaypointPath, WaypointPathError> {
+        WaypointPath::try_new(self.comp_waypoints(), turn_radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sector_pattern_returns_through_the_datum() {
+        let datum = Vector2::new(5.0, -3.0);
+        let mut search = SearchPattern::new(SearchPatternType::Sector, datum, 10.0, 9);
+        search.orientation = 0.3;
+        let waypoints = search.comp_waypoints();
+        assert_eq!(waypoints.len(), 10);
+
+        // back at the datum after every third leg, the other leg ends are on radial lines 60
+        // degrees apart
+        let mut directions = Vec::new();
+        for (i, waypoint) in waypoints.iter().enumerate() {
+            if i % 3 == 0 {
+                assert_eq!(*waypoint, datum);
+                continue;
+            }
+            let diff = waypoint - datum;
+            assert!((diff.norm() - 10.0).abs() < 1e-9);
+            let sector = (diff[1].atan2(diff[0]) - search.orientation) / (PI / 3.0);
+            assert!((sector - sector.round()).abs() < 1e-9);
+            directions.push(sector.round().rem_euclid(6.0) as usize);
+        }
+        directions.sort();
+        assert_eq!(directions, [0, 1, 2, 3, 4, 5]);
+
+        assert!(search.to_waypoint_path(2.0).is_ok());
+    }
+
+    #[test]
+    fn expanding_square_legs_grow_every_second_leg() {
+        let search = SearchPattern::new(
+            SearchPatternType::ExpandingSquare,
+            Vector2::zeros(),
+            10.0,
+            5,
+        );
+        let waypoints = search.comp_waypoints();
+        let lengths: Vec<f64> = waypoints.windows(2).map(|w| (w[1] - w[0]).norm()).collect();
+        for (length, expected) in lengths.iter().zip([10.0, 10.0, 20.0, 20.0, 30.0]) {
+            assert!((length - expected).abs() < 1e-9);
+        }
+    }
+}
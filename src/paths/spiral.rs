@@ -0,0 +1,342 @@
+extern crate nalgebra as na;
+
+use na::{Matrix2, Rotation2, UnitVector2, Vector2};
+
+use crate::paths::path::{comp_arc_length, comp_closest_theta, Path, PathSegment};
+
+const MIN_TURN_ANGLE: f64 = 1e-9;
+const SERIES_TERMS: usize = 12;
+const N_SAMPLES: usize = 16;
+// square root of the polar angle where the curvature of a Fermat spiral peaks
+const FERMAT_PEAK_PARAMETER: f64 = 0.519_571_856_327_912;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpiralType {
+    // curvature linear in the arc length
+    #[default]
+    Clothoid,
+    // r = a sqrt(phi), curvature grows with the square root of the arc length near the start
+    Fermat,
+}
+
+// Corner made of two mirrored spirals joining the tangent points on the incoming and outgoing
+// lines, with zero curvature at both ends and the largest curvature at the midpoint, or before it
+// for Fermat spirals turning more than the peak of their curvature. Theta runs from 0 to twice the
+// spiral parameter at the midpoint, the spirals are parametrized by the arc length for clothoids
+// and by the square root of the polar angle for Fermat spirals. Beyond the ends the corner
+// continues along the lines.
+#[allow(non_snake_case)]
+pub struct SpiralCorner {
+    spiral_type: SpiralType,
+    start: Vector2<f64>,
+    end: Vector2<f64>,
+    // from the local frame of the spiral starting at start and of the one ending at end
+    R_start: Matrix2<f64>,
+    R_end: Matrix2<f64>,
+    theta_mid: f64,
+    // A^2 of the clothoid or a of the Fermat spiral
+    scale: f64,
+    radius: f64,
+}
+
+#[allow(non_snake_case)]
+impl SpiralCorner {
+    // Corner at waypoint between the unit directions v and v_next, starting and ending at the
+    // corner distance from the waypoint. radius is the radius of the circular fillet with the
+    // same corner distance.
+    pub fn new(
+        spiral_type: SpiralType,
+        waypoint: Vector2<f64>,
+        v: &Vector2<f64>,
+        v_next: &Vector2<f64>,
+        corner_distance: f64,
+        radius: f64,
+    ) -> SpiralCorner {
+        let S = Matrix2::new(0.0, -1.0, 1.0, 0.0);
+        let q = (S * v).dot(v_next).signum();
+        let half_turn = 0.5 * v.dot(v_next).clamp(-1.0, 1.0).acos();
+
+        let mut corner = SpiralCorner {
+            spiral_type,
+            start: waypoint - corner_distance * v,
+            end: waypoint + corner_distance * v_next,
+            R_start: Rotation2::new(v[1].atan2(v[0])).into_inner() * Matrix2::new(1.0, 0.0, 0.0, q),
+            R_end: Rotation2::new(v_next[1].atan2(v_next[0])).into_inner()
+                * Matrix2::new(1.0, 0.0, 0.0, -q),
+            theta_mid: 0.0,
+            scale: 1.0,
+            radius,
+        };
+        if half_turn < MIN_TURN_ANGLE || corner_distance <= 0.0 {
+            return corner;
+        }
+
+        // unit sized spiral turning half_turn, the midpoint lies on the bisector of the corner
+        // when corner_distance = x + y tan(half_turn)
+        let (theta_mid, scale) = match spiral_type {
+            SpiralType::Clothoid => (1.0, 0.5 / half_turn),
+            SpiralType::Fermat => (comp_fermat_parameter(half_turn), 1.0),
+        };
+        corner.theta_mid = theta_mid;
+        corner.scale = scale;
+        let (pos_mid, _, _) = corner.eval_spiral(theta_mid);
+        let size = corner_distance / (pos_mid[0] + pos_mid[1] * half_turn.tan());
+        match spiral_type {
+            SpiralType::Clothoid => {
+                corner.theta_mid = size;
+                corner.scale = size.powi(2) * scale;
+            }
+            SpiralType::Fermat => corner.scale = size,
+        }
+        corner
+    }
+
+    pub fn get_radius(&self) -> f64 {
+        self.radius
+    }
+
+    // radius of curvature where the corner turns the tightest, the circular fillet radius when
+    // it does not turn
+    pub fn comp_min_radius(&self) -> f64 {
+        if self.theta_mid <= 0.0 {
+            return self.radius;
+        }
+        let theta = match self.spiral_type {
+            SpiralType::Clothoid => self.theta_mid,
+            SpiralType::Fermat => self.theta_mid.min(FERMAT_PEAK_PARAMETER),
+        };
+        1.0 / self.comp_curvature(theta).abs()
+    }
+
+    pub fn get_spiral_type(&self) -> SpiralType {
+        self.spiral_type
+    }
+
+    // position and first and second derivatives of the spiral in its local frame, starting at the
+    // origin along the x-axis and turning counter-clockwise
+    fn eval_spiral(&self, t: f64) -> (Vector2<f64>, Vector2<f64>, Vector2<f64>) {
+        match self.spiral_type {
+            SpiralType::Clothoid => {
+                // Fresnel integrals of the tangent angle phi = t^2 / (2 A^2)
+                let phi = t.powi(2) / (2.0 * self.scale);
+                let (mut c, mut s) = (1.0, phi);
+                let (mut x, mut y) = (0.0, 0.0);
+                for n in 0..SERIES_TERMS {
+                    let k = n as f64;
+                    x += c / (4.0 * k + 1.0);
+                    y += s / (4.0 * k + 3.0);
+                    c *= -phi.powi(2) / ((2.0 * k + 1.0) * (2.0 * k + 2.0));
+                    s *= -phi.powi(2) / ((2.0 * k + 2.0) * (2.0 * k + 3.0));
+                }
+                let tangent = Vector2::new(phi.cos(), phi.sin());
+                (
+                    t * Vector2::new(x, y),
+                    tangent,
+                    t / self.scale * Vector2::new(-tangent[1], tangent[0]),
+                )
+            }
+            SpiralType::Fermat => {
+                let a = self.scale;
+                let (sin, cos) = t.powi(2).sin_cos();
+                (
+                    a * t * Vector2::new(cos, sin),
+                    a * Vector2::new(cos - 2.0 * t.powi(2) * sin, sin + 2.0 * t.powi(2) * cos),
+                    a * Vector2::new(
+                        -6.0 * t * sin - 4.0 * t.powi(3) * cos,
+                        6.0 * t * cos - 4.0 * t.powi(3) * sin,
+                    ),
+                )
+            }
+        }
+    }
+
+    // position and first and second derivatives with respect to theta
+    fn eval(&self, theta: f64) -> (Vector2<f64>, Vector2<f64>, Vector2<f64>) {
+        let theta_end = 2.0 * self.theta_mid;
+        if theta < 0.0 {
+            let (_, dpos, _) = self.eval(0.0);
+            return (self.start + theta * dpos, dpos, Vector2::zeros());
+        } else if theta > theta_end {
+            let (_, dpos, _) = self.eval(theta_end);
+            return (
+                self.end + (theta - theta_end) * dpos,
+                dpos,
+                Vector2::zeros(),
+            );
+        }
+
+        if theta <= self.theta_mid {
+            let (pos, dpos, ddpos) = self.eval_spiral(theta);
+            (
+                self.start + self.R_start * pos,
+                self.R_start * dpos,
+                self.R_start * ddpos,
+            )
+        } else {
+            // the second half is the first one traversed backwards from the end
+            let (pos, dpos, ddpos) = self.eval_spiral(theta_end - theta);
+            (
+                self.end - self.R_end * pos,
+                self.R_end * dpos,
+                -(self.R_end * ddpos),
+            )
+        }
+    }
+
+    // arc length from the start of the corner to theta
+    pub fn comp_arc_length(&self, theta: f64) -> f64 {
+        let speed = |theta: f64| self.eval(theta).1.norm();
+        let theta = theta.clamp(0.0, 2.0 * self.theta_mid);
+        if theta <= self.theta_mid {
+            comp_arc_length(speed, 0.0, theta)
+        } else {
+            comp_arc_length(speed, 0.0, self.theta_mid)
+                + comp_arc_length(speed, self.theta_mid, theta)
+        }
+    }
+}
+
+// Parameter t = sqrt(phi) of the Fermat spiral where the tangent angle phi + atan(2 phi) equals
+// the given angle
+fn comp_fermat_parameter(angle: f64) -> f64 {
+    let mut phi = angle / 3.0;
+    for _ in 0..20 {
+        let f = phi + (2.0 * phi).atan() - angle;
+        phi -= f / (1.0 + 2.0 / (1.0 + 4.0 * phi.powi(2)));
+    }
+    phi.max(0.0).sqrt()
+}
+
+impl Path for SpiralCorner {
+    fn comp_theta(&mut self, pos: &Vector2<f64>) -> f64 {
+        let theta_end = 2.0 * self.theta_mid;
+        let theta = comp_closest_theta(|theta| self.eval(theta), pos, 0.0, theta_end, N_SAMPLES);
+
+        // project onto the lines when past the ends
+        if theta <= 0.0 {
+            let (pos_d, dpos_d, _) = self.eval(0.0);
+            ((pos - pos_d).dot(&dpos_d) / dpos_d.norm_squared()).min(0.0)
+        } else if theta >= theta_end {
+            let (pos_d, dpos_d, _) = self.eval(theta_end);
+            theta_end + ((pos - pos_d).dot(&dpos_d) / dpos_d.norm_squared()).max(0.0)
+        } else {
+            theta
+        }
+    }
+
+    fn comp_pos(&self, theta: f64) -> Vector2<f64> {
+        self.eval(theta).0
+    }
+
+    fn comp_tangent(&self, theta: f64) -> UnitVector2<f64> {
+        UnitVector2::new_normalize(self.eval(theta).1)
+    }
+
+    fn comp_curvature(&self, theta: f64) -> f64 {
+        let (_, dpos, ddpos) = self.eval(theta);
+        (dpos[0] * ddpos[1] - dpos[1] * ddpos[0]) / dpos.norm().powi(3)
+    }
+
+    fn comp_max_curvature_ahead(&self, theta: f64, distance: f64) -> f64 {
+        let theta_end = 2.0 * self.theta_mid;
+        let theta_min = theta.clamp(0.0, theta_end);
+        let theta_max = (theta + distance / self.comp_arc_length_derivative(theta_min))
+            .clamp(theta_min, theta_end);
+        (0..=N_SAMPLES)
+            .map(|k| {
+                self.comp_curvature(
+                    theta_min + (theta_max - theta_min) * k as f64 / N_SAMPLES as f64,
+                )
+                .abs()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    fn comp_arc_length_derivative(&self, theta: f64) -> f64 {
+        self.eval(theta).1.norm()
+    }
+
+    fn theta_range(&self) -> (f64, f64) {
+        (0.0, 2.0 * self.theta_mid)
+    }
+
+    fn total_length(&self) -> f64 {
+        self.comp_arc_length(2.0 * self.theta_mid)
+    }
+
+    fn comp_progress(&self, theta: f64) -> f64 {
+        self.comp_arc_length(theta) / self.total_length()
+    }
+
+    fn current_segment_type(&self) -> PathSegment {
+        PathSegment::Circle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // corners turning 30, 90 and 150 degrees to the left and 90 degrees to the right at the origin
+    fn corners(spiral_type: SpiralType) -> Vec<SpiralCorner> {
+        let v = Vector2::new(1.0, 0.0);
+        [30.0_f64, 90.0, 150.0, -90.0]
+            .iter()
+            .map(|angle| {
+                let (sin, cos) = angle.to_radians().sin_cos();
+                let v_next = Vector2::new(cos, sin);
+                // circular fillet with the same corner distance
+                let radius = 10.0 / (0.5 * angle.abs().to_radians()).tan();
+                SpiralCorner::new(spiral_type, Vector2::zeros(), &v, &v_next, 10.0, radius)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn corners_are_tangent_to_the_lines() {
+        for spiral_type in [SpiralType::Clothoid, SpiralType::Fermat] {
+            for corner in corners(spiral_type) {
+                let (_, theta_end) = corner.theta_range();
+                let v_next = (corner.end - corner.comp_pos(theta_end - 1e-3)).normalize();
+                assert!((corner.comp_pos(0.0) - corner.start).norm() < 1e-9);
+                assert!((corner.comp_pos(theta_end) - corner.end).norm() < 1e-9);
+                assert!((corner.comp_tangent(0.0).into_inner() - Vector2::x()).norm() < 1e-9);
+                assert!((corner.comp_tangent(theta_end).into_inner() - v_next).norm() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn curvature_is_continuous() {
+        for spiral_type in [SpiralType::Clothoid, SpiralType::Fermat] {
+            for corner in corners(spiral_type) {
+                let (_, theta_end) = corner.theta_range();
+                assert!(corner.comp_curvature(0.0).abs() < 1e-9);
+                assert!(corner.comp_curvature(theta_end).abs() < 1e-9);
+
+                let n = 200;
+                let step = theta_end / n as f64;
+                for k in 0..n {
+                    let theta = k as f64 * step;
+                    let jump = corner.comp_curvature(theta + step) - corner.comp_curvature(theta);
+                    assert!(jump.abs() < 0.02, "{:?} {} {}", spiral_type, theta, jump);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn min_radius_is_the_peak_curvature() {
+        for spiral_type in [SpiralType::Clothoid, SpiralType::Fermat] {
+            for corner in corners(spiral_type) {
+                let (_, theta_end) = corner.theta_range();
+                let max_curvature = (0..=1000)
+                    .map(|k| corner.comp_curvature(theta_end * k as f64 / 1000.0).abs())
+                    .fold(0.0, f64::max);
+                let min_radius = corner.comp_min_radius();
+                assert!(min_radius < corner.get_radius());
+                assert!((min_radius * max_curvature - 1.0).abs() < 1e-4);
+            }
+        }
+    }
+}
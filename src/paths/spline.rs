@@ -0,0 +1,354 @@
+extern crate nalgebra as na;
+
+use na::{UnitVector2, Vector2};
+
+use crate::paths::path::{comp_arc_length, comp_closest_theta, Path};
+
+use std::fmt;
+
+const MIN_KNOT_DISTANCE: f64 = 1e-6;
+const SAMPLES_PER_SEGMENT: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SplineError {
+    // number of distinct waypoints
+    TooFewWaypoints(usize),
+    NonFiniteWaypoint(usize),
+}
+
+impl fmt::Display for SplineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplineError::TooFewWaypoints(n) => write!(
+                f,
+                "a spline needs at least two distinct waypoints, got {}",
+                n
+            ),
+            SplineError::NonFiniteWaypoint(i) => write!(f, "waypoint {} is not finite", i),
+        }
+    }
+}
+
+impl std::error::Error for SplineError {}
+
+// Natural cubic spline through the waypoints, parametrized by the chord length between them.
+// Beyond the start the path continues along the start tangent, the reference stops at the end
+// point once it has been passed.
+pub struct CubicSpline {
+    theta: f64,
+    completed: bool,
+    knots: Vec<f64>,
+    // coefficients a, b, c, d of a + b s + c s^2 + d s^3 on each segment
+    coeffs: Vec<[Vector2<f64>; 4]>,
+    segment_lengths: Vec<f64>,
+    search_window: Option<f64>,
+}
+
+impl CubicSpline {
+    pub fn new(waypoints: Vec<Vector2<f64>>) -> CubicSpline {
+        Self::try_new(waypoints).unwrap_or_else(|e| panic!("Invalid spline: {}", e))
+    }
+
+    pub fn try_new(waypoints: Vec<Vector2<f64>>) -> Result<CubicSpline, SplineError> {
+        if let Some(i) = waypoints
+            .iter()
+            .position(|waypoint| !(waypoint[0].is_finite() && waypoint[1].is_finite()))
+        {
+            return Err(SplineError::NonFiniteWaypoint(i));
+        }
+
+        let mut points: Vec<Vector2<f64>> = Vec::new();
+        for waypoint in waypoints {
+            let distinct = match points.last() {
+                Some(last) => (waypoint - last).norm() > MIN_KNOT_DISTANCE,
+                None => true,
+            };
+            if distinct {
+                points.push(waypoint);
+            }
+        }
+        if points.len() < 2 {
+            return Err(SplineError::TooFewWaypoints(points.len()));
+        }
+
+        let n = points.len();
+        let mut knots = vec![0.0; n];
+        for i in 1..n {
+            knots[i] = knots[i - 1] + (points[i] - points[i - 1]).norm();
+        }
+        let h: Vec<f64> = (0..n - 1).map(|i| knots[i + 1] - knots[i]).collect();
+
+        // second derivatives at the knots, zero at the ends, from the tridiagonal system
+        // h[i-1] m[i-1] + 2 (h[i-1] + h[i]) m[i] + h[i] m[i+1] = 6 (slope[i] - slope[i-1])
+        let mut m = vec![Vector2::zeros(); n];
+        if n > 2 {
+            let slope: Vec<Vector2<f64>> = (0..n - 1)
+                .map(|i| (points[i + 1] - points[i]) / h[i])
+                .collect();
+            let mut diag = vec![0.0; n];
+            let mut rhs = vec![Vector2::zeros(); n];
+            for i in 1..n - 1 {
+                diag[i] = 2.0 * (h[i - 1] + h[i]);
+                rhs[i] = 6.0 * (slope[i] - slope[i - 1]);
+            }
+            // Thomas algorithm
+            for i in 2..n - 1 {
+                let w = h[i - 1] / diag[i - 1];
+                diag[i] -= w * h[i - 1];
+                rhs[i] = rhs[i] - w * rhs[i - 1];
+            }
+            m[n - 2] = rhs[n - 2] / diag[n - 2];
+            for i in (1..n - 2).rev() {
+                m[i] = (rhs[i] - h[i] * m[i + 1]) / diag[i];
+            }
+        }
+
+        let coeffs = (0..n - 1)
+            .map(|i| {
+                [
+                    points[i],
+                    (points[i + 1] - points[i]) / h[i] - h[i] * (2.0 * m[i] + m[i + 1]) / 6.0,
+                    m[i] / 2.0,
+                    (m[i + 1] - m[i]) / (6.0 * h[i]),
+                ]
+            })
+            .collect();
+
+        let mut spline = CubicSpline {
+            theta: 0.0,
+            completed: false,
+            knots,
+            coeffs,
+            segment_lengths: Vec::new(),
+            search_window: None,
+        };
+        spline.segment_lengths = (0..n - 1)
+            .map(|i| spline.comp_arc_length_between(spline.knots[i], spline.knots[i + 1]))
+            .collect();
+        Ok(spline)
+    }
+
+    // Limits the closest-point search to theta within the window around the previous theta,
+    // which keeps the projection from jumping between close parts of the path. None searches
+    // the whole path.
+    pub fn set_search_window(&mut self, search_window: Option<f64>) {
+        self.search_window = search_window;
+    }
+
+    fn comp_segment(&self, theta: f64) -> usize {
+        self.knots
+            .partition_point(|&knot| knot <= theta)
+            .saturating_sub(1)
+            .min(self.coeffs.len() - 1)
+    }
+
+    // position and first and second derivatives with respect to theta
+    fn eval(&self, theta: f64) -> (Vector2<f64>, Vector2<f64>, Vector2<f64>) {
+        let theta_end = self.knots[self.knots.len() - 1];
+        let (theta_clamped, extension) = if theta < 0.0 {
+            (0.0, theta)
+        } else if theta > theta_end {
+            (theta_end, theta - theta_end)
+        } else {
+            (theta, 0.0)
+        };
+
+        let i = self.comp_segment(theta_clamped);
+        let [a, b, c, d] = self.coeffs[i];
+        let s = theta_clamped - self.knots[i];
+        let pos = a + s * (b + s * (c + s * d));
+        let dpos = b + s * (2.0 * c + 3.0 * s * d);
+        if extension != 0.0 {
+            return (pos + extension * dpos, dpos, Vector2::zeros());
+        }
+        (pos, dpos, 2.0 * c + 6.0 * s * d)
+    }
+
+    fn comp_arc_length_between(&self, theta_0: f64, theta_1: f64) -> f64 {
+        comp_arc_length(|theta| self.eval(theta).1.norm(), theta_0, theta_1)
+    }
+
+    // arc length from the start of the spline to theta
+    fn comp_arc_length(&self, theta: f64) -> f64 {
+        let theta = theta.clamp(0.0, self.knots[self.knots.len() - 1]);
+        let i = self.comp_segment(theta);
+        self.segment_lengths[..i].iter().sum::<f64>()
+            + self.comp_arc_length_between(self.knots[i], theta)
+    }
+}
+
+impl Path for CubicSpline {
+    fn comp_theta(&mut self, pos: &Vector2<f64>) -> f64 {
+        let theta_end = self.knots[self.knots.len() - 1];
+        if self.completed {
+            return theta_end;
+        }
+        let (theta_min, theta_max) = match self.search_window {
+            Some(window) => (
+                (self.theta - window).clamp(0.0, theta_end),
+                (self.theta + window).clamp(0.0, theta_end),
+            ),
+            None => (0.0, theta_end),
+        };
+
+        let first = self.comp_segment(theta_min);
+        let last = self.comp_segment(theta_max);
+        let n_samples = (last - first + 1) * SAMPLES_PER_SEGMENT;
+        let mut theta = comp_closest_theta(
+            |theta| self.eval(theta),
+            pos,
+            theta_min,
+            theta_max,
+            n_samples,
+        );
+
+        // project onto the extensions when past the ends
+        if theta <= 0.0 {
+            let (pos_d, dpos_d, _) = self.eval(0.0);
+            theta = ((pos - pos_d).dot(&dpos_d) / dpos_d.norm_squared()).min(0.0);
+        } else if theta >= theta_end {
+            let (pos_d, dpos_d, _) = self.eval(theta_end);
+            if (pos - pos_d).dot(&dpos_d) > 0.0 {
+                self.completed = true;
+            }
+            theta = theta_end;
+        }
+
+        self.theta = theta;
+        theta
+    }
+
+    fn comp_pos(&self, theta: f64) -> Vector2<f64> {
+        self.eval(theta).0
+    }
+
+    fn comp_tangent(&self, theta: f64) -> UnitVector2<f64> {
+        UnitVector2::new_normalize(self.eval(theta).1)
+    }
+
+    fn comp_curvature(&self, theta: f64) -> f64 {
+        let (_, dpos, ddpos) = self.eval(theta);
+        (dpos[0] * ddpos[1] - dpos[1] * ddpos[0]) / dpos.norm().powi(3)
+    }
+
+    fn comp_max_curvature_ahead(&self, theta: f64, distance: f64) -> f64 {
+        // theta is close to the arc length, so sample the same distance in theta
+        let n_samples = SAMPLES_PER_SEGMENT * 4;
+        (0..=n_samples)
+            .map(|k| {
+                self.comp_curvature(theta + distance * k as f64 / n_samples as f64)
+                    .abs()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    fn comp_arc_length_derivative(&self, theta: f64) -> f64 {
+        self.eval(theta).1.norm()
+    }
+
+    fn theta_range(&self) -> (f64, f64) {
+        (0.0, self.knots[self.knots.len() - 1])
+    }
+
+    fn total_length(&self) -> f64 {
+        self.segment_lengths.iter().sum()
+    }
+
+    fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    fn comp_progress(&self, theta: f64) -> f64 {
+        if self.completed {
+            return 1.0;
+        }
+        self.comp_arc_length(theta) / self.total_length()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn waypoints() -> Vec<Vector2<f64>> {
+        vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(20.0, 10.0),
+            Vector2::new(35.0, -5.0),
+            Vector2::new(60.0, 0.0),
+            Vector2::new(70.0, 25.0),
+        ]
+    }
+
+    // position, first and second derivatives at the end of segment i - 1 and the start of i
+    fn eval_segment(spline: &CubicSpline, i: usize, s: f64) -> [Vector2<f64>; 3] {
+        let [a, b, c, d] = spline.coeffs[i];
+        [
+            a + s * (b + s * (c + s * d)),
+            b + s * (2.0 * c + 3.0 * s * d),
+            2.0 * c + 6.0 * s * d,
+        ]
+    }
+
+    #[test]
+    fn spline_is_twice_continuously_differentiable_through_the_waypoints() {
+        let points = waypoints();
+        let spline = CubicSpline::try_new(points.clone()).unwrap();
+        let n = points.len();
+
+        for (i, point) in points.iter().enumerate().take(n - 1).skip(1) {
+            let h = spline.knots[i] - spline.knots[i - 1];
+            let before = eval_segment(&spline, i - 1, h);
+            let after = eval_segment(&spline, i, 0.0);
+            for k in 0..3 {
+                assert!((before[k] - after[k]).norm() < 1e-9);
+            }
+            assert!((after[0] - point).norm() < 1e-9);
+        }
+
+        // natural end conditions
+        assert!(eval_segment(&spline, 0, 0.0)[2].norm() < 1e-9);
+        let h = spline.knots[n - 1] - spline.knots[n - 2];
+        let end = eval_segment(&spline, n - 2, h);
+        assert!((end[0] - points[n - 1]).norm() < 1e-9);
+        assert!(end[2].norm() < 1e-9);
+    }
+
+    #[test]
+    fn closest_point_is_the_normal_projection() {
+        let mut spline = CubicSpline::try_new(waypoints()).unwrap();
+        for theta in [5.0, 30.0, 55.0, 80.0] {
+            let normal = spline.comp_tangent(theta).into_inner();
+            let pos = spline.comp_pos(theta) + 2.0 * Vector2::new(-normal[1], normal[0]);
+            assert!((spline.comp_theta(&pos) - theta).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn spline_is_completed_past_the_end() {
+        let mut spline = CubicSpline::try_new(waypoints()).unwrap();
+        let (_, theta_end) = spline.theta_range();
+        let tangent = spline.comp_tangent(theta_end).into_inner();
+
+        spline.comp_theta(&(spline.comp_pos(theta_end) - tangent));
+        assert!(!spline.is_completed());
+        assert_eq!(
+            spline.comp_theta(&(spline.comp_pos(theta_end) + tangent)),
+            theta_end
+        );
+        assert!(spline.is_completed());
+    }
+
+    #[test]
+    fn try_new_rejects_invalid_waypoints() {
+        let point = Vector2::new(1.0, 2.0);
+        assert_eq!(
+            CubicSpline::try_new(vec![point, point]).err(),
+            Some(SplineError::TooFewWaypoints(1))
+        );
+        assert_eq!(
+            CubicSpline::try_new(vec![point, Vector2::new(f64::NAN, 0.0)]).err(),
+            Some(SplineError::NonFiniteWaypoint(1))
+        );
+    }
+}
@@ -0,0 +1,292 @@
+extern crate nalgebra as na;
+
+use na::Vector2;
+
+use crate::paths::composite::CompositePath;
+use crate::paths::waypoint_path::{WaypointPath, WaypointPathError};
+
+use std::f64::consts::PI;
+use std::fmt;
+
+const MIN_LINE_LENGTH: f64 = 1e-6;
+
+// start and end of a survey line
+pub type SurveyLine = (Vector2<f64>, Vector2<f64>);
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+// written as a map with the rectangle corners or as a list of polygon vertices
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum SurveyArea {
+    // axis-aligned rectangle given by two opposite corners
+    Rectangle {
+        corner_0: Vector2<f64>,
+        corner_1: Vector2<f64>,
+    },
+    Polygon(Vec<Vector2<f64>>),
+}
+
+impl SurveyArea {
+    pub fn get_vertices(&self) -> Vec<Vector2<f64>> {
+        match self {
+            SurveyArea::Rectangle { corner_0, corner_1 } => vec![
+                *corner_0,
+                Vector2::new(corner_1[0], corner_0[1]),
+                *corner_1,
+                Vector2::new(corner_0[0], corner_1[1]),
+            ],
+            SurveyArea::Polygon(vertices) => vertices.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SurveyError {
+    TooFewVertices(usize),
+    InvalidSpacing(f64),
+    // no survey line crosses the area
+    EmptyArea,
+    // the U-turns between neighbouring lines are tighter than the turn radius
+    TurnRadiusTooLarge(f64),
+    InvalidPath(WaypointPathError),
+}
+
+impl fmt::Display for SurveyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SurveyError::TooFewVertices(n) => {
+                write!(f, "survey area needs at least 3 vertices, got {}", n)
+            }
+            SurveyError::InvalidSpacing(spacing) => write!(f, "invalid line spacing: {}", spacing),
+            SurveyError::EmptyArea => write!(f, "no survey line crosses the area"),
+            SurveyError::TurnRadiusTooLarge(radius) => write!(
+                f,
+                "turn radius {} does not fit between neighbouring lines",
+                radius
+            ),
+            SurveyError::InvalidPath(e) => write!(f, "invalid survey path: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SurveyError {}
+
+// Lawnmower (boustrophedon) survey with parallel lines at the given spacing, running along the
+// orientation angle from the x-axis and traversed in alternating directions.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LawnmowerSurvey {
+    pub area: SurveyArea,
+    pub line_spacing: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub orientation: f64,
+    pub turn_radius: f64,
+}
+
+impl LawnmowerSurvey {
+    pub fn new(
+        area: SurveyArea,
+        line_spacing: f64,
+        orientation: f64,
+        turn_radius: f64,
+    ) -> LawnmowerSurvey {
+        LawnmowerSurvey {
+            area,
+            line_spacing,
+            orientation,
+            turn_radius,
+        }
+    }
+
+    fn comp_directions(&self) -> (Vector2<f64>, Vector2<f64>) {
+        let (sin, cos) = self.orientation.sin_cos();
+        (Vector2::new(cos, sin), Vector2::new(-sin, cos))
+    }
+
+    // Start and end of each survey line in the order they are run. The lines are centered in
+    // strips of the line spacing across the area, a line crossing a non-convex area more than
+    // once spans from its first to its last crossing.
+    pub fn comp_survey_lines(&self) -> Result<Vec<SurveyLine>, SurveyError> {
+        let vertices = self.area.get_vertices();
+        let n = vertices.len();
+        if n < 3 {
+            return Err(SurveyError::TooFewVertices(n));
+        }
+        if !(self.line_spacing.is_finite() && self.line_spacing > 0.0) {
+            return Err(SurveyError::InvalidSpacing(self.line_spacing));
+        }
+
+        let (along, across) = self.comp_directions();
+        let offsets: Vec<f64> = vertices.iter().map(|vertex| vertex.dot(&across)).collect();
+        let offset_min = offsets.iter().cloned().fold(f64::INFINITY, f64::min);
+        let offset_max = offsets.iter().cloned().fold(-f64::INFINITY, f64::max);
+        let width = offset_max - offset_min;
+        let n_lines = ((width / self.line_spacing).ceil() as usize).max(1);
+        let first_offset = offset_min + 0.5 * (width - (n_lines - 1) as f64 * self.line_spacing);
+
+        let mut lines = Vec::new();
+        for k in 0..n_lines {
+            let offset = first_offset + k as f64 * self.line_spacing;
+            let crossings: Vec<f64> = (0..n)
+                .filter_map(|j| {
+                    let (a, b) = (vertices[j], vertices[(j + 1) % n]);
+                    let (side_a, side_b) = (offsets[j] - offset, offsets[(j + 1) % n] - offset);
+                    if (side_a < 0.0) == (side_b < 0.0) {
+                        return None;
+                    }
+                    Some((a + (b - a) * side_a / (side_a - side_b)).dot(&along))
+                })
+                .collect();
+            let start = crossings.iter().cloned().fold(f64::INFINITY, f64::min);
+            let end = crossings.iter().cloned().fold(-f64::INFINITY, f64::max);
+            if crossings.len() < 2 || end - start < MIN_LINE_LENGTH {
+                continue;
+            }
+
+            let (start, end) = match lines.len() % 2 {
+                0 => (start, end),
+                _ => (end, start),
+            };
+            lines.push((
+                start * along + offset * across,
+                end * along + offset * across,
+            ));
+        }
+
+        if lines.is_empty() {
+            return Err(SurveyError::EmptyArea);
+        }
+        Ok(lines)
+    }
+
+    pub fn comp_waypoints(&self) -> Result<Vec<Vector2<f64>>, SurveyError> {
+        Ok(self
+            .comp_survey_lines()?
+            .into_iter()
+            .flat_map(|(start, end)| [start, end])
+            .collect())
+    }
+
+    // lines joined by transit legs between the line ends, with fillets of the turn radius
+    pub fn to_waypoint_path(&self) -> Result<WaypointPath, SurveyError> {
+        WaypointPath::try_new(self.comp_waypoints()?, self.turn_radius)
+            .map_err(SurveyError::InvalidPath)
+    }
+
+    // Lines joined by half circles with a diameter of the line spacing. The shorter of two
+    // neighbouring lines is extended so that the turn starts and ends abreast.
+    pub fn to_u_turn_path(&self) -> Result<CompositePath, SurveyError> {
+        if 0.5 * self.line_spacing < self.turn_radius {
+            return Err(SurveyError::TurnRadiusTooLarge(self.turn_radius));
+        }
+        let lines = self.comp_survey_lines()?;
+        let (along, across) = self.comp_directions();
+
+        let mut path = CompositePath::new();
+        let mut start = lines[0].0;
+        for (k, &(_, end)) in lines.iter().enumerate() {
+            let next_start = match lines.get(k + 1) {
+                Some(&(next_start, _)) => next_start,
+                None => {
+                    path.push_line(start, end);
+                    break;
+                }
+            };
+
+            // along-track position of the turn, beyond both line ends
+            let (sign, angle) = match k % 2 {
+                0 => (1.0, PI),
+                _ => (-1.0, -PI),
+            };
+            let turn = sign * (sign * end.dot(&along)).max(sign * next_start.dot(&along));
+            let end = end + (turn - end.dot(&along)) * along;
+            path.push_line(start, end);
+
+            let center = end + 0.5 * (next_start - end).dot(&across) * across;
+            start = path.push_arc(end, center, angle);
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::path::Path;
+
+    fn assert_lines(lines: &[SurveyLine], expected: &[[f64; 4]]) {
+        assert_eq!(lines.len(), expected.len());
+        for ((start, end), [x0, y0, x1, y1]) in lines.iter().zip(expected) {
+            assert!(
+                (start - Vector2::new(*x0, *y0)).norm() < 1e-9,
+                "{:?}",
+                lines
+            );
+            assert!((end - Vector2::new(*x1, *y1)).norm() < 1e-9, "{:?}", lines);
+        }
+    }
+
+    fn rectangle() -> SurveyArea {
+        SurveyArea::Rectangle {
+            corner_0: Vector2::new(0.0, 0.0),
+            corner_1: Vector2::new(100.0, 35.0),
+        }
+    }
+
+    #[test]
+    fn rectangle_lines_are_centered_and_alternate() {
+        let survey = LawnmowerSurvey::new(rectangle(), 10.0, 0.0, 4.0);
+        assert_lines(
+            &survey.comp_survey_lines().unwrap(),
+            &[
+                [0.0, 2.5, 100.0, 2.5],
+                [100.0, 12.5, 0.0, 12.5],
+                [0.0, 22.5, 100.0, 22.5],
+                [100.0, 32.5, 0.0, 32.5],
+            ],
+        );
+
+        let path = survey.to_u_turn_path().unwrap();
+        assert_eq!(path.len(), 7);
+        assert!((path.total_length() - (400.0 + 3.0 * PI * 5.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn non_convex_lines_span_all_crossings() {
+        // U-shaped area open towards positive y
+        let area = SurveyArea::Polygon(vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(30.0, 0.0),
+            Vector2::new(30.0, 30.0),
+            Vector2::new(20.0, 30.0),
+            Vector2::new(20.0, 10.0),
+            Vector2::new(10.0, 10.0),
+            Vector2::new(10.0, 30.0),
+            Vector2::new(0.0, 30.0),
+        ]);
+        let survey = LawnmowerSurvey::new(area, 10.0, 0.0, 4.0);
+        assert_lines(
+            &survey.comp_survey_lines().unwrap(),
+            &[
+                [0.0, 5.0, 30.0, 5.0],
+                [30.0, 15.0, 0.0, 15.0],
+                [0.0, 25.0, 30.0, 25.0],
+            ],
+        );
+    }
+
+    #[test]
+    fn invalid_surveys_are_rejected() {
+        let survey = LawnmowerSurvey::new(rectangle(), 10.0, 0.0, 6.0);
+        assert!(matches!(
+            survey.to_u_turn_path(),
+            Err(SurveyError::TurnRadiusTooLarge(_))
+        ));
+
+        let survey = LawnmowerSurvey::new(rectangle(), 0.0, 0.0, 4.0);
+        assert_eq!(
+            survey.comp_survey_lines(),
+            Err(SurveyError::InvalidSpacing(0.0))
+        );
+    }
+}
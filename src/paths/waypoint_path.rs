@@ -1,22 +1,298 @@
 extern crate nalgebra as na;
 
 // use crate::circle::Circle;
+use crate::ilos::ssa;
 use crate::paths::circle::Circle;
 use crate::paths::line::Line;
-use crate::paths::path::Path;
+use crate::paths::path::{EndBehavior, LegMetadata, Path, PathSegment};
+use crate::paths::spiral::{SpiralCorner, SpiralType};
 use na::{Matrix2, UnitVector2, Vector2};
 
-#[derive(Clone, Debug)]
-enum PathSegment {
-    Line,
+use std::fmt;
+use std::ops::Range;
+
+const MIN_HOLD_DISTANCE: f64 = 1e-3;
+const MIN_WAYPOINT_DISTANCE: f64 = 1e-6;
+const REVERSAL_TOLERANCE: f64 = 1e-9;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Waypoint {
+    pub pos: Vector2<f64>,
+    // radius of the fillet at this waypoint, the path default is used if None
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub turn_radius: Option<f64>,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub leg: LegMetadata,
+}
+
+impl Waypoint {
+    pub fn new(pos: Vector2<f64>) -> Waypoint {
+        Waypoint {
+            pos,
+            turn_radius: None,
+            leg: LegMetadata::default(),
+        }
+    }
+}
+
+// shape of the turns at the waypoints
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "zenoh", derive(clap::ValueEnum))]
+pub enum CornerType {
+    // circular fillet, the curvature jumps at the tangent points
+    #[default]
     Circle,
+    // pair of clothoids, the curvature ramps linearly from zero
+    Clothoid,
+    // pair of Fermat spirals
+    Fermat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WaypointPathError {
+    TooFewWaypoints(usize),
+    InvalidIndex(usize),
+    InvalidRadius(usize, f64),
+    NonFiniteWaypoint(usize),
+    // waypoint equal to the previous one
+    DuplicateWaypoint(usize),
+    // the path turns back on itself at the waypoint
+    Reversal(usize),
+    // the fillets at both ends of the leg ending at the waypoint do not fit on it
+    OverlappingFillets(usize),
+}
+
+impl fmt::Display for WaypointPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaypointPathError::TooFewWaypoints(n) => write!(f, "too few waypoints: {}", n),
+            WaypointPathError::InvalidIndex(i) => write!(f, "waypoint index out of range: {}", i),
+            WaypointPathError::InvalidRadius(i, radius) => {
+                write!(f, "invalid turn radius at waypoint {}: {}", i, radius)
+            }
+            WaypointPathError::NonFiniteWaypoint(i) => {
+                write!(f, "non-finite waypoint at index {}", i)
+            }
+            WaypointPathError::DuplicateWaypoint(i) => {
+                write!(f, "duplicate waypoint at index {}", i)
+            }
+            WaypointPathError::Reversal(i) => write!(f, "path reverses at waypoint {}", i),
+            WaypointPathError::OverlappingFillets(i) => {
+                write!(f, "fillets overlap on the leg ending at waypoint {}", i)
+            }
+        }
+    }
+}
+
+impl std::error::Error for WaypointPathError {}
+
+fn to_waypoints(points: Vec<Vector2<f64>>) -> Vec<Waypoint> {
+    points.into_iter().map(Waypoint::new).collect()
 }
 
+// distance from the waypoint to where the fillet arc meets the incoming and outgoing lines
+#[allow(non_snake_case)]
+fn comp_fillet_distance(v: &Vector2<f64>, v_next: &Vector2<f64>, circle_radius: f64) -> f64 {
+    let S = Matrix2::new(0.0, -1.0, 1.0, 0.0);
+    circle_radius * ((S * v).dot(v_next)).abs() / (1.0 + v.dot(v_next))
+}
+
+// Turn radius at each waypoint, its own or the default one. With auto_radius the radii are shrunk
+// so that the fillets at both ends of every leg fit on it, legs that are too short are shared in
+// proportion to the requested fillets.
+fn comp_turn_radii(
+    waypoints: &[Waypoint],
+    circle_radius: f64,
+    closed: bool,
+    auto_radius: bool,
+) -> Vec<f64> {
+    let n = waypoints.len();
+    let radii: Vec<f64> = waypoints
+        .iter()
+        .map(|w| w.turn_radius.unwrap_or(circle_radius))
+        .collect();
+    if !auto_radius || n < 3 {
+        return radii;
+    }
+    let n_corners = if closed { n } else { n - 2 };
+    let n_legs = if closed { n } else { n - 1 };
+
+    // fillet distance per unit radius, zero for straight corners and reversals
+    let mut fillet_factors = vec![0.0; n];
+    for i in 0..n_corners {
+        let j = (i + 1) % n;
+        let v = waypoints[j].pos - waypoints[i].pos;
+        let v_next = waypoints[(i + 2) % n].pos - waypoints[j].pos;
+        if v.norm() < MIN_WAYPOINT_DISTANCE || v_next.norm() < MIN_WAYPOINT_DISTANCE {
+            continue;
+        }
+        let (v, v_next) = (v.normalize(), v_next.normalize());
+        if 1.0 + v.dot(&v_next) >= REVERSAL_TOLERANCE {
+            fillet_factors[j] = comp_fillet_distance(&v, &v_next, 1.0);
+        }
+    }
+
+    let fillet_distances: Vec<f64> = (0..n).map(|i| radii[i] * fillet_factors[i]).collect();
+    let mut max_distances = fillet_distances.clone();
+    for i in 1..=n_legs {
+        let (a, b) = (i - 1, i % n);
+        let length = (waypoints[b].pos - waypoints[a].pos).norm();
+        let total = fillet_distances[a] + fillet_distances[b];
+        if total > length {
+            max_distances[a] = max_distances[a].min(fillet_distances[a] * length / total);
+            max_distances[b] = max_distances[b].min(fillet_distances[b] * length / total);
+        }
+    }
+
+    (0..n)
+        .map(|j| {
+            if max_distances[j] < fillet_distances[j] {
+                max_distances[j] / fillet_factors[j]
+            } else {
+                radii[j]
+            }
+        })
+        .collect()
+}
+
+// turn_radii are the radii the fillets are built with, the requested ones have to be valid
+fn validate(
+    waypoints: &[Waypoint],
+    circle_radius: f64,
+    turn_radii: &[f64],
+    closed: bool,
+) -> Result<(), WaypointPathError> {
+    let n = waypoints.len();
+    if n < 2 || (closed && n < 3) {
+        return Err(WaypointPathError::TooFewWaypoints(n));
+    }
+    if let Some(i) = waypoints
+        .iter()
+        .position(|waypoint| !waypoint.pos.iter().all(|x| x.is_finite()))
+    {
+        return Err(WaypointPathError::NonFiniteWaypoint(i));
+    }
+
+    let n_legs = if closed { n } else { n - 1 };
+    for i in 1..=n_legs {
+        if (waypoints[i % n].pos - waypoints[i - 1].pos).norm() < MIN_WAYPOINT_DISTANCE {
+            return Err(WaypointPathError::DuplicateWaypoint(i % n));
+        }
+    }
+
+    // fillet distance at the start and end of each leg
+    let mut fillet_distances = vec![0.0; n];
+    let n_corners = if closed { n } else { n - 2 };
+    for i in 0..n_corners {
+        let waypoint = &waypoints[(i + 1) % n];
+        let radius = waypoint.turn_radius.unwrap_or(circle_radius);
+        if !radius.is_finite() || radius <= 0.0 {
+            return Err(WaypointPathError::InvalidRadius((i + 1) % n, radius));
+        }
+        let v = (waypoint.pos - waypoints[i].pos).normalize();
+        let v_next = (waypoints[(i + 2) % n].pos - waypoint.pos).normalize();
+        if 1.0 + v.dot(&v_next) < REVERSAL_TOLERANCE {
+            return Err(WaypointPathError::Reversal((i + 1) % n));
+        }
+        fillet_distances[(i + 1) % n] = comp_fillet_distance(&v, &v_next, turn_radii[(i + 1) % n]);
+    }
+    for i in 1..=n_legs {
+        let length = (waypoints[i % n].pos - waypoints[i - 1].pos).norm();
+        if fillet_distances[i - 1] + fillet_distances[i % n] > length + MIN_WAYPOINT_DISTANCE {
+            return Err(WaypointPathError::OverlappingFillets(i % n));
+        }
+    }
+    Ok(())
+}
+
+// turn joining two lines at a waypoint
+enum Corner {
+    Circle(Circle),
+    Spiral(SpiralCorner),
+}
+
+impl Corner {
+    // smallest radius of curvature along the turn
+    fn comp_min_radius(&self) -> f64 {
+        match self {
+            Corner::Circle(circle) => circle.get_radius(),
+            Corner::Spiral(spiral) => spiral.comp_min_radius(),
+        }
+    }
+}
+
+impl Path for Corner {
+    fn comp_theta(&mut self, pos: &Vector2<f64>) -> f64 {
+        match self {
+            Corner::Circle(circle) => circle.comp_theta(pos),
+            Corner::Spiral(spiral) => spiral.comp_theta(pos),
+        }
+    }
+
+    fn comp_pos(&self, theta: f64) -> Vector2<f64> {
+        match self {
+            Corner::Circle(circle) => circle.comp_pos(theta),
+            Corner::Spiral(spiral) => spiral.comp_pos(theta),
+        }
+    }
+
+    fn comp_tangent(&self, theta: f64) -> UnitVector2<f64> {
+        match self {
+            Corner::Circle(circle) => circle.comp_tangent(theta),
+            Corner::Spiral(spiral) => spiral.comp_tangent(theta),
+        }
+    }
+
+    fn comp_curvature(&self, theta: f64) -> f64 {
+        match self {
+            Corner::Circle(circle) => circle.comp_curvature(theta),
+            Corner::Spiral(spiral) => spiral.comp_curvature(theta),
+        }
+    }
+
+    fn comp_max_curvature_ahead(&self, theta: f64, distance: f64) -> f64 {
+        match self {
+            Corner::Circle(circle) => circle.comp_max_curvature_ahead(theta, distance),
+            Corner::Spiral(spiral) => spiral.comp_max_curvature_ahead(theta, distance),
+        }
+    }
+
+    fn comp_arc_length_derivative(&self, theta: f64) -> f64 {
+        match self {
+            Corner::Circle(circle) => circle.comp_arc_length_derivative(theta),
+            Corner::Spiral(spiral) => spiral.comp_arc_length_derivative(theta),
+        }
+    }
+}
+
+// line or corner with its theta bounds
+type LineSegment = (Line, f64, f64);
+type CornerSegment = (Corner, f64, f64);
+
 pub struct WaypointPath {
+    waypoints: Vec<Waypoint>,
+    circle_radius: f64,
+    closed: bool,
+    // shrink the turn radii that do not fit on short legs
+    auto_radius: bool,
+    // radii the turns at the waypoints are built with
+    turn_radii: Vec<f64>,
+    corner_type: CornerType,
+    end_behavior: EndBehavior,
+    completed: bool,
+    laps: usize,
+    // why a looping or reversing path could not start over and stopped instead
+    restart_error: Option<WaypointPathError>,
+    // a closed path only wraps back to the closing fillet once line 0 has been left
+    started: bool,
+    end_tangent: UnitVector2<f64>,
+    relocalization_threshold: f64,
+    relocalization_hysteresis: f64,
     current_waypoint: usize,
     current_path_segment: PathSegment,
     lines: Vec<Line>,
-    circles: Vec<Circle>,
+    circles: Vec<Corner>,
     theta_circ_min: Vec<f64>,
     theta_circ_max: Vec<f64>,
     theta_line_min: Vec<f64>,
@@ -26,90 +302,573 @@ pub struct WaypointPath {
 #[allow(non_snake_case)]
 impl WaypointPath {
     pub fn new(waypoints: Vec<Vector2<f64>>, circle_radius: f64) -> WaypointPath {
-        let mut lines = Vec::new();
-        let mut circles = Vec::new();
-        let mut theta_circ_min = Vec::new();
-        let mut theta_circ_max = Vec::new();
-        let mut theta_line_min = Vec::new();
-        let mut theta_line_max = Vec::new();
+        Self::build(to_waypoints(waypoints), circle_radius, false, false)
+    }
+
+    // connects the last waypoint back to the first one with a line and fillet arcs
+    pub fn new_closed(waypoints: Vec<Vector2<f64>>, circle_radius: f64) -> WaypointPath {
+        Self::build(to_waypoints(waypoints), circle_radius, true, false)
+    }
+
+    pub fn try_new(
+        waypoints: Vec<Vector2<f64>>,
+        circle_radius: f64,
+    ) -> Result<WaypointPath, WaypointPathError> {
+        Self::try_from_waypoints(to_waypoints(waypoints), circle_radius, false)
+    }
 
+    pub fn try_new_closed(
+        waypoints: Vec<Vector2<f64>>,
+        circle_radius: f64,
+    ) -> Result<WaypointPath, WaypointPathError> {
+        Self::try_from_waypoints(to_waypoints(waypoints), circle_radius, true)
+    }
+
+    // circle_radius is used for waypoints without their own turn radius
+    pub fn try_from_waypoints(
+        waypoints: Vec<Waypoint>,
+        circle_radius: f64,
+        closed: bool,
+    ) -> Result<WaypointPath, WaypointPathError> {
+        Self::try_build(waypoints, circle_radius, closed, false)
+    }
+
+    // like try_from_waypoints, but shrinks the turn radii that do not fit on short legs, also
+    // after waypoint edits
+    pub fn try_from_waypoints_auto_radius(
+        waypoints: Vec<Waypoint>,
+        circle_radius: f64,
+        closed: bool,
+    ) -> Result<WaypointPath, WaypointPathError> {
+        Self::try_build(waypoints, circle_radius, closed, true)
+    }
+
+    fn try_build(
+        waypoints: Vec<Waypoint>,
+        circle_radius: f64,
+        closed: bool,
+        auto_radius: bool,
+    ) -> Result<WaypointPath, WaypointPathError> {
+        let turn_radii = comp_turn_radii(&waypoints, circle_radius, closed, auto_radius);
+        validate(&waypoints, circle_radius, &turn_radii, closed)?;
+        Ok(Self::build(waypoints, circle_radius, closed, auto_radius))
+    }
+
+    fn build(
+        waypoints: Vec<Waypoint>,
+        circle_radius: f64,
+        closed: bool,
+        auto_radius: bool,
+    ) -> WaypointPath {
+        let n = waypoints.len();
+        let turn_radii = comp_turn_radii(&waypoints, circle_radius, closed, auto_radius);
+        let mut path = WaypointPath {
+            waypoints,
+            circle_radius,
+            closed,
+            auto_radius,
+            turn_radii,
+            corner_type: CornerType::Circle,
+            end_behavior: EndBehavior::Stop,
+            completed: false,
+            laps: 0,
+            restart_error: None,
+            started: false,
+            end_tangent: Vector2::x_axis(),
+            relocalization_threshold: f64::INFINITY,
+            relocalization_hysteresis: 1.0,
+            current_waypoint: 1,
+            current_path_segment: PathSegment::Line,
+            lines: Vec::new(),
+            circles: Vec::new(),
+            theta_circ_min: Vec::new(),
+            theta_circ_max: Vec::new(),
+            theta_line_min: Vec::new(),
+            theta_line_max: Vec::new(),
+        };
+        path.assemble(
+            (0..n).map(|_| None).collect(),
+            (0..n).map(|_| None).collect(),
+        );
+        path
+    }
+
+    // Computes the missing lines (indexed by their first waypoint) and circles (indexed by their
+    // waypoint) with their theta bounds and stores all segments in path order.
+    fn assemble(
+        &mut self,
+        mut line_from: Vec<Option<LineSegment>>,
+        mut corner_at: Vec<Option<CornerSegment>>,
+    ) {
         let S = Matrix2::new(0.0, -1.0, 1.0, 0.0);
 
-        theta_line_min.push(-f64::INFINITY);
+        let points: Vec<Vector2<f64>> = self.waypoints.iter().map(|w| w.pos).collect();
+        let n = points.len();
+        let n_lines = if self.closed { n } else { n - 1 };
+        let closed = self.closed;
+        let is_corner = |j: usize| closed || (j > 0 && j < n - 1);
 
-        for i in 1..waypoints.len() {
-            let line: Line = Line::new(waypoints[i - 1], waypoints[i]);
-            lines.push(line);
+        for j in 0..n {
+            if j >= n_lines {
+                line_from[j] = None;
+            } else if line_from[j].is_none() {
+                let line: Line = Line::new(points[j], points[(j + 1) % n]);
+                let length = line.total_length();
+                line_from[j] = Some((line, -f64::INFINITY, length));
+            }
+            if !is_corner(j) {
+                corner_at[j] = None;
+            }
         }
-        let mut v = (waypoints[1] - waypoints[0]).normalize();
 
-        for i in 0..waypoints.len() - 2 {
-            let v_next = (waypoints[i + 2] - waypoints[i + 1]).normalize();
-            let d = circle_radius * ((S * v).dot(&v_next)).abs() / (1.0 + v.dot(&v_next));
+        // the circle at waypoint j joins the line ending and the line starting at it
+        for j in 0..n {
+            if !is_corner(j) || corner_at[j].is_some() {
+                continue;
+            }
+            let (prev, next) = ((j + n - 1) % n, (j + 1) % n);
+            let waypoint = points[j];
+            let radius = self.turn_radii[j];
+            let v = (waypoint - points[prev]).normalize();
+            let v_next = (points[next] - waypoint).normalize();
+            let d = comp_fillet_distance(&v, &v_next, radius);
             let q = (S * v).dot(&v_next).signum();
             let clockwise = q < 0.0;
 
-            let theta_max_line = lines[i].comp_theta(&(waypoints[i + 1] - d * v));
-            let theta_min_line = lines[i + 1].comp_theta(&(waypoints[i + 1] + d * v_next));
-            theta_line_min.push(theta_min_line);
+            if let Some((line, _, theta_max_line)) = line_from[prev].as_mut() {
+                *theta_max_line = line.comp_theta(&(waypoint - d * v));
+            }
+            if let Some((line, theta_min_line, _)) = line_from[j].as_mut() {
+                *theta_min_line = line.comp_theta(&(waypoint + d * v_next));
+            }
+
+            let spiral_type = match self.corner_type {
+                CornerType::Circle => None,
+                CornerType::Clothoid => Some(SpiralType::Clothoid),
+                CornerType::Fermat => Some(SpiralType::Fermat),
+            };
+            corner_at[j] = Some(match spiral_type {
+                None => {
+                    let pos_circle = waypoint - d * v + q * radius * S * v;
+                    let mut circle: Circle = Circle::new(radius, pos_circle, clockwise);
+
+                    let theta_min_circ = circle.comp_theta(&(waypoint - d * v));
+                    // unwrapped so that the arc does not jump across +-pi
+                    let theta_max_circ = theta_min_circ
+                        + ssa(circle.comp_theta(&(waypoint + d * v_next)) - theta_min_circ);
+                    (Corner::Circle(circle), theta_min_circ, theta_max_circ)
+                }
+                Some(spiral_type) => {
+                    let spiral = SpiralCorner::new(spiral_type, waypoint, &v, &v_next, d, radius);
+                    let (theta_min_circ, theta_max_circ) = spiral.theta_range();
+                    (Corner::Spiral(spiral), theta_min_circ, theta_max_circ)
+                }
+            });
+        }
+
+        if !closed {
+            if let Some((_, theta_min_line, _)) = line_from[0].as_mut() {
+                *theta_min_line = -f64::INFINITY;
+            }
+            if let Some((line, _, theta_max_line)) = line_from[n_lines - 1].as_mut() {
+                *theta_max_line = line.total_length();
+            }
+        }
 
-            theta_line_max.push(theta_max_line);
+        self.lines.clear();
+        self.theta_line_min.clear();
+        self.theta_line_max.clear();
+        for (line, theta_min, theta_max) in line_from.into_iter().flatten() {
+            self.lines.push(line);
+            self.theta_line_min.push(theta_min);
+            self.theta_line_max.push(theta_max);
+        }
 
-            let pos_circle = waypoints[i + 1] - d * v + q * circle_radius * S * v;
-            let circle: Circle = Circle::new(circle_radius, pos_circle, clockwise);
-            circles.push(circle);
+        // circle i comes after line i, at waypoint i + 1
+        corner_at.rotate_left(1);
+        self.circles.clear();
+        self.theta_circ_min.clear();
+        self.theta_circ_max.clear();
+        for (circle, theta_min, theta_max) in corner_at.into_iter().flatten() {
+            self.circles.push(circle);
+            self.theta_circ_min.push(theta_min);
+            self.theta_circ_max.push(theta_max);
+        }
 
-            let theta_min_circ = circles[i].comp_theta(&(waypoints[i + 1] - d * v));
-            let theta_max_circ = circles[i].comp_theta(&(waypoints[i + 1] + d * v_next));
+        self.end_tangent = self.lines[self.lines.len() - 1].comp_tangent(0.0);
+    }
 
-            theta_circ_min.push(theta_min_circ);
-            theta_circ_max.push(theta_max_circ);
+    // inverse of assemble, takes the segments out of the path
+    fn disassemble(&mut self) -> (Vec<Option<LineSegment>>, Vec<Option<CornerSegment>>) {
+        let n = self.waypoints.len();
+        let mut line_from: Vec<Option<LineSegment>> = self
+            .lines
+            .drain(..)
+            .zip(self.theta_line_min.drain(..))
+            .zip(self.theta_line_max.drain(..))
+            .map(|((line, theta_min), theta_max)| Some((line, theta_min, theta_max)))
+            .collect();
+        line_from.resize_with(n, || None);
 
-            v = v_next;
+        let mut corner_at: Vec<Option<CornerSegment>> = self
+            .circles
+            .drain(..)
+            .zip(self.theta_circ_min.drain(..))
+            .zip(self.theta_circ_max.drain(..))
+            .map(|((circle, theta_min), theta_max)| Some((circle, theta_min, theta_max)))
+            .collect();
+        corner_at.resize_with(n, || None);
+        corner_at.rotate_right(1);
+
+        (line_from, corner_at)
+    }
+
+    // Replaces the waypoints in range, rebuilding only the segments next to them. The tracked
+    // segment is kept, or moved to the segment replacing it.
+    fn splice_waypoints(
+        &mut self,
+        range: Range<usize>,
+        replacement: Vec<Waypoint>,
+    ) -> Result<(), WaypointPathError> {
+        let n = self.waypoints.len();
+        if range.start > range.end || range.end > n {
+            return Err(WaypointPathError::InvalidIndex(range.start));
         }
+        let m = replacement.len();
+        let mut waypoints = self.waypoints.clone();
+        waypoints.splice(range.clone(), replacement);
+        let turn_radii = comp_turn_radii(
+            &waypoints,
+            self.circle_radius,
+            self.closed,
+            self.auto_radius,
+        );
+        validate(&waypoints, self.circle_radius, &turn_radii, self.closed)?;
+        let n_new = waypoints.len();
 
-        theta_line_max.push(f64::INFINITY);
+        // tracked segment, identified by the waypoint the line starts at or the circle turns at
+        let (segment_type, j) = match self.current_path_segment {
+            PathSegment::Line => (PathSegment::Line, self.current_waypoint - 1),
+            PathSegment::Circle => (PathSegment::Circle, self.current_waypoint % n),
+        };
+        let (segment_type, j) = if j < range.start {
+            (segment_type, j)
+        } else if j >= range.end {
+            (segment_type, j + m - range.len())
+        } else if m > 0 {
+            (segment_type, range.start)
+        } else {
+            // removed, continue on the line towards the waypoint after the removed ones
+            match range.start {
+                0 if !self.closed => (PathSegment::Line, 0),
+                _ => (PathSegment::Line, (range.start + n_new - 1) % n_new),
+            }
+        };
 
-        WaypointPath {
-            // waypoints,
-            current_waypoint: 1,
-            current_path_segment: PathSegment::Line,
-            lines,
-            circles,
-            theta_circ_min,
-            theta_circ_max,
-            theta_line_min,
-            theta_line_max,
+        let (mut line_from, mut corner_at) = self.disassemble();
+        line_from.splice(range.clone(), (0..m).map(|_| None));
+        corner_at.splice(range.clone(), (0..m).map(|_| None));
+        self.waypoints = waypoints;
+
+        // shrunk radii can change at the turns next to the edit as well
+        let mut turn_radii_prev = std::mem::replace(&mut self.turn_radii, turn_radii);
+        turn_radii_prev.splice(range.clone(), (0..m).map(|_| f64::NAN));
+        for (j, corner) in corner_at.iter_mut().enumerate() {
+            if turn_radii_prev[j] != self.turn_radii[j] {
+                *corner = None;
+            }
+        }
+
+        let (a, b) = (range.start, range.start + m);
+        if a > 0 || self.closed {
+            let prev = (a + n_new - 1) % n_new;
+            line_from[prev] = None;
+            corner_at[prev] = None;
+        }
+        if b < n_new || self.closed {
+            corner_at[b % n_new] = None;
+        }
+        self.assemble(line_from, corner_at);
+
+        let is_corner = self.closed || (j > 0 && j < n_new - 1);
+        match segment_type {
+            PathSegment::Circle if is_corner => {
+                self.current_waypoint = (j + n_new - 1) % n_new + 1;
+                self.current_path_segment = PathSegment::Circle;
+            }
+            _ => {
+                self.current_waypoint = j.min(self.lines.len() - 1) + 1;
+                self.current_path_segment = PathSegment::Line;
+            }
+        }
+        self.completed = false;
+        Ok(())
+    }
+
+    pub fn append_waypoint(&mut self, waypoint: Waypoint) -> Result<(), WaypointPathError> {
+        let n = self.waypoints.len();
+        self.splice_waypoints(n..n, vec![waypoint])
+    }
+
+    // inserts the waypoint before the waypoint at index
+    pub fn insert_waypoint(
+        &mut self,
+        index: usize,
+        waypoint: Waypoint,
+    ) -> Result<(), WaypointPathError> {
+        self.splice_waypoints(index..index, vec![waypoint])
+    }
+
+    pub fn remove_waypoint(&mut self, index: usize) -> Result<(), WaypointPathError> {
+        self.splice_waypoints(index..index + 1, Vec::new())
+    }
+
+    pub fn replace_waypoint(
+        &mut self,
+        index: usize,
+        waypoint: Waypoint,
+    ) -> Result<(), WaypointPathError> {
+        self.splice_waypoints(index..index + 1, vec![waypoint])
+    }
+
+    pub fn get_waypoints(&self) -> &[Waypoint] {
+        &self.waypoints
+    }
+
+    pub fn set_end_behavior(&mut self, end_behavior: EndBehavior) {
+        self.end_behavior = end_behavior;
+    }
+
+    // rebuilds the turns at all waypoints with the given shape
+    pub fn set_corner_type(&mut self, corner_type: CornerType) {
+        self.corner_type = corner_type;
+        let (line_from, corner_at) = self.disassemble();
+        let n = corner_at.len();
+        self.assemble(line_from, (0..n).map(|_| None).collect());
+    }
+
+    pub fn get_corner_type(&self) -> CornerType {
+        self.corner_type
+    }
+
+    // Effective radius of the turn at each corner, the smallest radius of curvature for spirals,
+    // which is tighter than the radius of the circular fillet they replace
+    pub fn get_turn_radii(&self) -> Vec<f64> {
+        self.circles
+            .iter()
+            .map(|corner| corner.comp_min_radius())
+            .collect()
+    }
+
+    // the vessel is within the acceptance radius of the waypoint that line i ends at
+    fn comp_leg_accepted(&self, i: usize, pos: &Vector2<f64>) -> bool {
+        let end = &self.waypoints[(i + 1) % self.waypoints.len()];
+        match self.waypoints[i].leg.acceptance_radius {
+            Some(radius) => (end.pos - pos).norm() < radius,
+            None => false,
+        }
+    }
+
+    fn end_path(&mut self) {
+        let waypoints = match self.end_behavior {
+            EndBehavior::Stop | EndBehavior::Hold => {
+                self.completed = true;
+                return;
+            }
+            EndBehavior::Loop => {
+                // transit from the final waypoint back to the first one and continue from there,
+                // the transit leg carries the metadata of the final waypoint
+                let n = self.waypoints.len();
+                let mut waypoints = self.waypoints.clone();
+                if (waypoints[0].pos - waypoints[n - 1].pos).norm() > MIN_HOLD_DISTANCE {
+                    waypoints.insert(0, waypoints[n - 1].clone());
+                }
+                waypoints
+            }
+            EndBehavior::Reverse => {
+                // leg metadata moves to the other end of each leg
+                let n = self.waypoints.len();
+                let mut waypoints = self.waypoints.clone();
+                for i in (1..n).rev() {
+                    waypoints[i].leg = waypoints[i - 1].leg.clone();
+                }
+                waypoints[0].leg = LegMetadata::default();
+                waypoints.reverse();
+                waypoints
+            }
+        };
+
+        // stop at the final waypoint if the transit leg turns back or its fillets do not fit
+        match self.rebuild(waypoints) {
+            Ok(()) => self.laps += 1,
+            Err(e) => {
+                self.restart_error = Some(e);
+                self.end_behavior = EndBehavior::Stop;
+                self.completed = true;
+            }
+        }
+    }
+
+    pub fn get_restart_error(&self) -> Option<WaypointPathError> {
+        self.restart_error
+    }
+
+    // rebuilds an open path from the given waypoints keeping the settings
+    fn rebuild(&mut self, waypoints: Vec<Waypoint>) -> Result<(), WaypointPathError> {
+        let turn_radii = comp_turn_radii(&waypoints, self.circle_radius, false, self.auto_radius);
+        validate(&waypoints, self.circle_radius, &turn_radii, false)?;
+        let mut path = WaypointPath::build(waypoints, self.circle_radius, false, self.auto_radius);
+        path.set_corner_type(self.corner_type);
+        path.end_behavior = self.end_behavior;
+        path.laps = self.laps;
+        path.relocalization_threshold = self.relocalization_threshold;
+        path.relocalization_hysteresis = self.relocalization_hysteresis;
+        *self = path;
+        Ok(())
+    }
+
+    // Relocalize when the distance to the current segment exceeds the threshold, only switching
+    // to a segment that is closer by more than the hysteresis.
+    pub fn set_relocalization(&mut self, threshold: f64, hysteresis: f64) {
+        self.relocalization_threshold = threshold;
+        self.relocalization_hysteresis = hysteresis;
+    }
+
+    // Moves to the globally closest segment, returns true if the segment changed
+    pub fn relocalize(&mut self, pos: &Vector2<f64>) -> bool {
+        let current_segment = self.current_segment();
+        let current_distance = self.comp_segment_distance(current_segment, pos);
+        let (closest_segment, closest_distance) = (0..self.lines.len() + self.circles.len())
+            .map(|segment| (segment, self.comp_segment_distance(segment, pos)))
+            .fold((current_segment, current_distance), |closest, candidate| {
+                if candidate.1 < closest.1 {
+                    candidate
+                } else {
+                    closest
+                }
+            });
+
+        if closest_segment == current_segment
+            || closest_distance > current_distance - self.relocalization_hysteresis
+        {
+            return false;
+        }
+        self.current_waypoint = closest_segment / 2 + 1;
+        self.current_path_segment = match closest_segment % 2 {
+            0 => PathSegment::Line,
+            _ => PathSegment::Circle,
+        };
+        true
+    }
+
+    // distance to the closest point of the segment within its theta bounds
+    fn comp_segment_distance(&mut self, segment: usize, pos: &Vector2<f64>) -> f64 {
+        let i = segment / 2;
+        match segment % 2 {
+            0 => {
+                let theta = self.lines[i]
+                    .comp_theta(pos)
+                    .max(self.theta_line_min[i])
+                    .min(self.theta_line_max[i]);
+                (pos - self.lines[i].comp_pos(theta)).norm()
+            }
+            _ => {
+                let theta = self
+                    .comp_circle_theta(i, pos)
+                    .max(self.theta_circ_min[i])
+                    .min(self.theta_circ_max[i]);
+                (pos - self.circles[i].comp_pos(theta)).norm()
+            }
+        }
+    }
+
+    fn comp_segment_length(&self, segment: usize) -> f64 {
+        // even segments are lines, odd segments are circles
+        let i = segment / 2;
+        match segment % 2 {
+            0 => {
+                let (theta_min, theta_max) = self.lines[i].theta_range();
+                self.theta_line_max[i].min(theta_max) - self.theta_line_min[i].max(theta_min)
+            }
+            _ => self.comp_corner_length(i, self.theta_circ_max[i]),
+        }
+    }
+
+    // arc length along corner i up to theta
+    fn comp_corner_length(&self, i: usize, theta: f64) -> f64 {
+        match &self.circles[i] {
+            Corner::Circle(circle) => circle.get_radius() * (theta - self.theta_circ_min[i]),
+            Corner::Spiral(spiral) => spiral.comp_arc_length(theta),
+        }
+    }
+
+    fn comp_circle_theta(&mut self, i: usize, pos: &Vector2<f64>) -> f64 {
+        match &mut self.circles[i] {
+            Corner::Circle(circle) => {
+                let theta_mid = 0.5 * (self.theta_circ_min[i] + self.theta_circ_max[i]);
+                theta_mid + ssa(circle.comp_theta(pos) - theta_mid)
+            }
+            Corner::Spiral(spiral) => spiral.comp_theta(pos),
         }
     }
 }
 
 impl Path for WaypointPath {
     fn comp_theta(&mut self, pos: &Vector2<f64>) -> f64 {
+        if self.completed {
+            let diff = self.waypoints[self.waypoints.len() - 1].pos - pos;
+            if self.end_behavior == EndBehavior::Hold && diff.norm() > MIN_HOLD_DISTANCE {
+                self.end_tangent = UnitVector2::new_normalize(diff);
+            }
+            return self.theta_line_max[self.lines.len() - 1];
+        }
+
+        if self.comp_segment_distance(self.current_segment(), pos) > self.relocalization_threshold {
+            self.relocalize(pos);
+        }
+
         match self.current_path_segment {
             PathSegment::Line => {
                 let theta = self.lines[self.current_waypoint - 1].comp_theta(pos);
-                if theta > self.theta_line_max[self.current_waypoint - 1] {
-                    self.current_path_segment = PathSegment::Circle;
-                } else if theta < self.theta_line_min[self.current_waypoint - 1] {
-                    self.current_waypoint -= 1;
+                if theta > self.theta_line_max[self.current_waypoint - 1]
+                    || self.comp_leg_accepted(self.current_waypoint - 1, pos)
+                {
+                    if self.current_waypoint > self.circles.len() {
+                        self.end_path();
+                        if self.completed {
+                            return self.comp_theta(pos);
+                        }
+                    } else {
+                        self.current_path_segment = PathSegment::Circle;
+                    }
+                } else if theta < self.theta_line_min[self.current_waypoint - 1]
+                    && (self.current_waypoint > 1 || self.started)
+                {
+                    self.current_waypoint = match self.current_waypoint {
+                        1 => self.lines.len(),
+                        _ => self.current_waypoint - 1,
+                    };
                     self.current_path_segment = PathSegment::Circle;
                 }
             }
             PathSegment::Circle => {
-                let theta = self.circles[self.current_waypoint - 1].comp_theta(pos);
+                let theta = self.comp_circle_theta(self.current_waypoint - 1, pos);
                 if theta > self.theta_circ_max[self.current_waypoint - 1] {
-                    self.current_waypoint += 1;
+                    self.current_waypoint = self.current_waypoint % self.lines.len() + 1;
                     self.current_path_segment = PathSegment::Line;
-                } else if theta < self.theta_circ_min[self.current_waypoint - 1] {
+                } else if theta < self.theta_circ_min[self.current_waypoint - 1]
+                    && !self.comp_leg_accepted(self.current_waypoint - 1, pos)
+                {
                     self.current_path_segment = PathSegment::Line;
                 }
             }
         };
 
+        self.started |= self.current_segment() > 0;
+
         let theta = match self.current_path_segment {
             PathSegment::Line => self.lines[self.current_waypoint - 1].comp_theta(pos),
-            PathSegment::Circle => self.circles[self.current_waypoint - 1].comp_theta(pos),
+            PathSegment::Circle => self.comp_circle_theta(self.current_waypoint - 1, pos),
         };
         theta
     }
@@ -123,10 +882,519 @@ impl Path for WaypointPath {
     }
 
     fn comp_tangent(&self, theta: f64) -> UnitVector2<f64> {
+        if self.completed {
+            return self.end_tangent;
+        }
         let tau = match self.current_path_segment {
             PathSegment::Line => self.lines[self.current_waypoint - 1].comp_tangent(theta),
             PathSegment::Circle => self.circles[self.current_waypoint - 1].comp_tangent(theta),
         };
         tau
     }
+
+    fn comp_curvature(&self, theta: f64) -> f64 {
+        match self.current_path_segment {
+            PathSegment::Line => self.lines[self.current_waypoint - 1].comp_curvature(theta),
+            PathSegment::Circle => self.circles[self.current_waypoint - 1].comp_curvature(theta),
+        }
+    }
+
+    fn comp_max_curvature_ahead(&self, theta: f64, distance: f64) -> f64 {
+        let i = self.current_waypoint - 1;
+        let (mut curvature, distance_to_end) = match self.current_path_segment {
+            PathSegment::Line => (0.0, self.theta_line_max[i] - theta),
+            PathSegment::Circle => (
+                self.circles[i].comp_max_curvature_ahead(theta, distance),
+                self.comp_corner_length(i, self.theta_circ_max[i])
+                    - self.comp_corner_length(i, theta),
+            ),
+        };
+
+        // distance left beyond the end of each segment, short legs may be passed entirely
+        let n_segments = self.lines.len() + self.circles.len();
+        let mut remaining = distance - distance_to_end.max(0.0);
+        let mut segment = self.current_segment();
+        for _ in 1..n_segments {
+            if remaining <= 0.0 || (segment + 1 == n_segments && !self.closed) {
+                break;
+            }
+            segment = (segment + 1) % n_segments;
+            if segment % 2 == 1 {
+                let j = segment / 2;
+                curvature = curvature.max(
+                    self.circles[j].comp_max_curvature_ahead(self.theta_circ_min[j], remaining),
+                );
+            }
+            remaining -= self.comp_segment_length(segment);
+        }
+        curvature
+    }
+
+    fn comp_arc_length_derivative(&self, theta: f64) -> f64 {
+        match self.current_path_segment {
+            PathSegment::Line => {
+                self.lines[self.current_waypoint - 1].comp_arc_length_derivative(theta)
+            }
+            PathSegment::Circle => {
+                self.circles[self.current_waypoint - 1].comp_arc_length_derivative(theta)
+            }
+        }
+    }
+
+    fn theta_range(&self) -> (f64, f64) {
+        let i = self.current_waypoint - 1;
+        match self.current_path_segment {
+            PathSegment::Line => (self.theta_line_min[i], self.theta_line_max[i]),
+            PathSegment::Circle => (self.theta_circ_min[i], self.theta_circ_max[i]),
+        }
+    }
+
+    // length from the first to the last waypoint along the lines and fillets
+    fn total_length(&self) -> f64 {
+        (0..self.lines.len() + self.circles.len())
+            .map(|segment| self.comp_segment_length(segment))
+            .sum()
+    }
+
+    fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    fn get_end_behavior(&self) -> EndBehavior {
+        self.end_behavior
+    }
+
+    fn completed_laps(&self) -> usize {
+        self.laps
+    }
+
+    // fillets belong to the leg they turn onto
+    fn current_leg(&self) -> Option<&LegMetadata> {
+        let n = self.waypoints.len();
+        match self.current_path_segment {
+            PathSegment::Line => Some(&self.waypoints[self.current_waypoint - 1].leg),
+            PathSegment::Circle => Some(&self.waypoints[self.current_waypoint % n].leg),
+        }
+    }
+
+    fn comp_progress(&self, theta: f64) -> f64 {
+        if self.completed {
+            return 1.0;
+        }
+        let segment = self.current_segment();
+        let i = segment / 2;
+        let travelled_on_segment = match self.current_path_segment {
+            PathSegment::Line => theta - self.theta_line_min[i].max(0.0),
+            PathSegment::Circle => self.comp_corner_length(i, theta),
+        };
+        let travelled: f64 = (0..segment)
+            .map(|segment| self.comp_segment_length(segment))
+            .sum::<f64>()
+            + travelled_on_segment;
+        (travelled / self.total_length()).clamp(0.0, 1.0)
+    }
+
+    fn current_segment(&self) -> usize {
+        match self.current_path_segment {
+            PathSegment::Line => 2 * (self.current_waypoint - 1),
+            PathSegment::Circle => 2 * (self.current_waypoint - 1) + 1,
+        }
+    }
+
+    fn current_segment_type(&self) -> PathSegment {
+        self.current_path_segment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square() -> Vec<Vector2<f64>> {
+        vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(100.0, 0.0),
+            Vector2::new(100.0, 100.0),
+            Vector2::new(0.0, 100.0),
+        ]
+    }
+
+    #[test]
+    fn closed_path_starts_on_the_first_line_behind_the_first_waypoint() {
+        let mut path = WaypointPath::try_new_closed(square(), 10.0).unwrap();
+        path.comp_theta(&Vector2::new(-20.0, -5.0));
+        assert_eq!(path.current_segment(), 0);
+
+        path.comp_theta(&Vector2::new(50.0, -5.0));
+        path.comp_theta(&Vector2::new(100.0, -5.0));
+        assert_eq!(path.current_segment(), 1);
+    }
+
+    #[test]
+    fn closed_path_wraps_to_the_closing_fillet_after_a_lap() {
+        let mut path = WaypointPath::try_new_closed(square(), 10.0).unwrap();
+        let corners = square();
+        for i in 0..4 {
+            let (a, b) = (corners[i], corners[(i + 1) % 4]);
+            for k in 0..100 {
+                path.comp_theta(&(a + (b - a) * k as f64 / 100.0));
+            }
+        }
+        path.comp_theta(&Vector2::new(50.0, 0.0));
+        assert_eq!(path.current_segment(), 0);
+
+        path.comp_theta(&Vector2::new(2.0, -5.0));
+        assert_eq!(path.current_segment(), 7);
+    }
+
+    fn l_shape() -> Vec<Vector2<f64>> {
+        vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(100.0, 0.0),
+            Vector2::new(100.0, 100.0),
+        ]
+    }
+
+    // walks along the waypoints and past the final one
+    fn drive_past_the_end(path: &mut WaypointPath, points: &[Vector2<f64>]) {
+        for i in 0..points.len() - 1 {
+            let (a, b) = (points[i], points[i + 1]);
+            for k in 0..100 {
+                path.comp_theta(&(a + (b - a) * k as f64 / 100.0));
+            }
+        }
+        let n = points.len();
+        let overshoot = (points[n - 1] - points[n - 2]).normalize() * 10.0;
+        path.comp_theta(&(points[n - 1] + overshoot));
+    }
+
+    #[test]
+    fn loop_starts_over_from_the_first_waypoint() {
+        let mut path = WaypointPath::try_new(l_shape(), 10.0).unwrap();
+        path.set_end_behavior(EndBehavior::Loop);
+        drive_past_the_end(&mut path, &l_shape());
+
+        assert!(!path.is_completed());
+        assert_eq!(path.completed_laps(), 1);
+        assert_eq!(path.waypoints[0].pos, l_shape()[2]);
+        assert_eq!(path.waypoints[1].pos, l_shape()[0]);
+    }
+
+    #[test]
+    fn reverse_traverses_the_waypoints_backwards() {
+        let mut path = WaypointPath::try_new(l_shape(), 10.0).unwrap();
+        path.set_end_behavior(EndBehavior::Reverse);
+        drive_past_the_end(&mut path, &l_shape());
+
+        assert!(!path.is_completed());
+        assert_eq!(path.completed_laps(), 1);
+        let mut reversed = l_shape();
+        reversed.reverse();
+        assert_same_segments(&path, reversed);
+    }
+
+    #[test]
+    fn hold_steers_back_to_the_final_waypoint() {
+        let mut path = WaypointPath::try_new(l_shape(), 10.0).unwrap();
+        path.set_end_behavior(EndBehavior::Hold);
+        drive_past_the_end(&mut path, &l_shape());
+
+        assert!(path.is_completed());
+        assert_eq!(path.completed_laps(), 0);
+        path.comp_theta(&Vector2::new(110.0, 100.0));
+        assert!((path.comp_tangent(0.0).into_inner() - Vector2::new(-1.0, 0.0)).norm() < 1e-9);
+    }
+
+    #[test]
+    fn loop_stops_if_the_transit_leg_turns_back() {
+        let points = vec![Vector2::new(0.0, 0.0), Vector2::new(100.0, 0.0)];
+        let mut path = WaypointPath::try_new(points.clone(), 10.0).unwrap();
+        path.set_end_behavior(EndBehavior::Loop);
+        drive_past_the_end(&mut path, &points);
+
+        assert!(path.is_completed());
+        assert_eq!(path.get_end_behavior(), EndBehavior::Stop);
+        assert_eq!(
+            path.get_restart_error(),
+            Some(WaypointPathError::Reversal(1))
+        );
+    }
+
+    #[test]
+    fn progress_grows_along_the_path() {
+        let mut path = WaypointPath::try_new(l_shape(), 10.0).unwrap();
+        let theta = path.comp_theta(&Vector2::new(45.0, 3.0));
+        let fillet = 10.0 * std::f64::consts::FRAC_PI_2;
+        let total_length = 180.0 + fillet;
+        assert!((path.total_length() - total_length).abs() < 1e-9);
+        assert!((path.comp_progress(theta) - 45.0 / total_length).abs() < 1e-9);
+
+        drive_past_the_end(&mut path, &l_shape());
+        assert_eq!(path.comp_progress(0.0), 1.0);
+    }
+
+    #[test]
+    fn acceptance_radius_completes_the_leg() {
+        let mut waypoints = to_waypoints(vec![Vector2::new(0.0, 0.0), Vector2::new(100.0, 0.0)]);
+        waypoints[0].leg.acceptance_radius = Some(5.0);
+        let mut path = WaypointPath::try_from_waypoints(waypoints, 10.0, false).unwrap();
+
+        path.comp_theta(&Vector2::new(94.0, 3.0));
+        assert!(!path.is_completed());
+        path.comp_theta(&Vector2::new(97.0, 3.0));
+        assert!(path.is_completed());
+    }
+
+    fn zigzag() -> Vec<Vector2<f64>> {
+        vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(100.0, 0.0),
+            Vector2::new(100.0, 100.0),
+            Vector2::new(200.0, 100.0),
+        ]
+    }
+
+    // tracking the line from waypoint 1 to waypoint 2
+    fn zigzag_on_second_line() -> WaypointPath {
+        let mut path = WaypointPath::try_new(zigzag(), 10.0).unwrap();
+        path.current_waypoint = 2;
+        path.current_path_segment = PathSegment::Line;
+        path
+    }
+
+    fn assert_same_segments(path: &WaypointPath, waypoints: Vec<Vector2<f64>>) {
+        let expected = WaypointPath::try_new(waypoints, 10.0).unwrap();
+        assert_eq!(path.theta_line_min, expected.theta_line_min);
+        assert_eq!(path.theta_line_max, expected.theta_line_max);
+        assert_eq!(path.theta_circ_min, expected.theta_circ_min);
+        assert_eq!(path.theta_circ_max, expected.theta_circ_max);
+    }
+
+    #[test]
+    fn relocalization_jumps_to_the_closest_segment() {
+        let mut path = WaypointPath::try_new(zigzag(), 10.0).unwrap();
+        path.set_relocalization(20.0, 1.0);
+        path.comp_theta(&Vector2::new(150.0, 105.0));
+        assert_eq!(path.current_segment(), 4);
+    }
+
+    #[test]
+    fn relocalization_keeps_the_segment_within_the_hysteresis() {
+        // the second line is 20 m away, 10 m closer than the first one
+        let mut path = WaypointPath::try_new(zigzag(), 10.0).unwrap();
+        path.set_relocalization(5.0, 15.0);
+        path.comp_theta(&Vector2::new(80.0, 30.0));
+        assert_eq!(path.current_segment(), 0);
+
+        path.set_relocalization(5.0, 5.0);
+        path.comp_theta(&Vector2::new(80.0, 30.0));
+        assert_eq!(path.current_segment(), 2);
+    }
+
+    #[test]
+    fn relocalization_is_disabled_by_an_infinite_threshold() {
+        let mut path = WaypointPath::try_new(zigzag(), 10.0).unwrap();
+        path.set_relocalization(f64::INFINITY, 1.0);
+        path.comp_theta(&Vector2::new(150.0, 105.0));
+        // past the end of the first line, on to the next turn
+        assert_eq!(path.current_segment(), 1);
+    }
+
+    #[test]
+    fn append_waypoint_keeps_the_tracked_segment() {
+        let mut path = zigzag_on_second_line();
+        let pos = Vector2::new(200.0, 200.0);
+        path.append_waypoint(Waypoint::new(pos)).unwrap();
+
+        assert_eq!(path.current_segment(), 2);
+        let mut waypoints = zigzag();
+        waypoints.push(pos);
+        assert_same_segments(&path, waypoints);
+    }
+
+    #[test]
+    fn insert_waypoint_shifts_the_tracked_segment() {
+        let mut path = zigzag_on_second_line();
+        let pos = Vector2::new(50.0, -50.0);
+        path.insert_waypoint(1, Waypoint::new(pos)).unwrap();
+
+        assert_eq!(path.current_segment(), 4);
+        let mut waypoints = zigzag();
+        waypoints.insert(1, pos);
+        assert_same_segments(&path, waypoints);
+    }
+
+    #[test]
+    fn remove_waypoint_moves_to_the_line_towards_the_next_waypoint() {
+        let mut path = zigzag_on_second_line();
+        path.remove_waypoint(1).unwrap();
+
+        assert_eq!(path.current_segment(), 0);
+        let mut waypoints = zigzag();
+        waypoints.remove(1);
+        assert_same_segments(&path, waypoints);
+    }
+
+    #[test]
+    fn replace_waypoint_keeps_the_tracked_segment() {
+        let mut path = zigzag_on_second_line();
+        let pos = Vector2::new(120.0, 100.0);
+        path.replace_waypoint(2, Waypoint::new(pos)).unwrap();
+
+        assert_eq!(path.current_segment(), 2);
+        let mut waypoints = zigzag();
+        waypoints[2] = pos;
+        assert_same_segments(&path, waypoints);
+    }
+
+    #[test]
+    fn invalid_edits_leave_the_path_unchanged() {
+        let mut path = zigzag_on_second_line();
+        assert_eq!(
+            path.remove_waypoint(4),
+            Err(WaypointPathError::InvalidIndex(4))
+        );
+        assert!(path
+            .replace_waypoint(2, Waypoint::new(zigzag()[1]))
+            .is_err());
+
+        assert_eq!(path.current_segment(), 2);
+        assert_same_segments(&path, zigzag());
+    }
+
+    #[test]
+    fn validate_rejects_too_few_waypoints() {
+        assert_eq!(
+            WaypointPath::try_new(vec![Vector2::zeros()], 10.0).err(),
+            Some(WaypointPathError::TooFewWaypoints(1))
+        );
+        assert_eq!(
+            WaypointPath::try_new_closed(zigzag()[..2].to_vec(), 10.0).err(),
+            Some(WaypointPathError::TooFewWaypoints(2))
+        );
+    }
+
+    #[test]
+    fn validate_rejects_non_finite_waypoints() {
+        let mut points = zigzag();
+        points[2].x = f64::NAN;
+        assert_eq!(
+            WaypointPath::try_new(points, 10.0).err(),
+            Some(WaypointPathError::NonFiniteWaypoint(2))
+        );
+    }
+
+    #[test]
+    fn validate_rejects_duplicate_waypoints() {
+        let mut points = zigzag();
+        points.insert(2, points[1]);
+        assert_eq!(
+            WaypointPath::try_new(points, 10.0).err(),
+            Some(WaypointPathError::DuplicateWaypoint(2))
+        );
+    }
+
+    #[test]
+    fn validate_rejects_invalid_radii() {
+        assert_eq!(
+            WaypointPath::try_new(zigzag(), 0.0).err(),
+            Some(WaypointPathError::InvalidRadius(1, 0.0))
+        );
+        let mut waypoints = to_waypoints(zigzag());
+        waypoints[2].turn_radius = Some(f64::INFINITY);
+        assert_eq!(
+            WaypointPath::try_from_waypoints(waypoints, 10.0, false).err(),
+            Some(WaypointPathError::InvalidRadius(2, f64::INFINITY))
+        );
+    }
+
+    #[test]
+    fn validate_rejects_reversals() {
+        let points = vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(100.0, 0.0),
+            Vector2::new(50.0, 0.0),
+        ];
+        assert_eq!(
+            WaypointPath::try_new(points, 10.0).err(),
+            Some(WaypointPathError::Reversal(1))
+        );
+    }
+
+    #[test]
+    fn validate_rejects_overlapping_fillets() {
+        // the fillets at both ends of the 100 m leg need 60 m each
+        assert_eq!(
+            WaypointPath::try_new(zigzag(), 60.0).err(),
+            Some(WaypointPathError::OverlappingFillets(2))
+        );
+    }
+
+    // a 20 m leg between two right-angle turns
+    fn short_leg() -> Vec<Waypoint> {
+        to_waypoints(vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(100.0, 0.0),
+            Vector2::new(100.0, 20.0),
+            Vector2::new(200.0, 20.0),
+        ])
+    }
+
+    #[test]
+    fn auto_radius_shares_short_legs_between_the_turns() {
+        assert_eq!(
+            WaypointPath::try_from_waypoints(short_leg(), 30.0, false).err(),
+            Some(WaypointPathError::OverlappingFillets(2))
+        );
+        let path = WaypointPath::try_from_waypoints_auto_radius(short_leg(), 30.0, false).unwrap();
+        assert_eq!(path.get_turn_radii(), vec![10.0, 10.0]);
+    }
+
+    #[test]
+    fn auto_radius_is_applied_after_edits() {
+        let mut path =
+            WaypointPath::try_from_waypoints_auto_radius(short_leg(), 30.0, false).unwrap();
+        let pos = Vector2::new(100.0, 40.0);
+        path.replace_waypoint(2, Waypoint::new(pos)).unwrap();
+        let mut waypoints = short_leg();
+        waypoints[2].pos = pos;
+        let expected =
+            WaypointPath::try_from_waypoints_auto_radius(waypoints, 30.0, false).unwrap();
+        assert!(path.get_turn_radii()[0] > 10.0);
+        assert_eq!(path.get_turn_radii(), expected.get_turn_radii());
+        assert_eq!(path.theta_line_max, expected.theta_line_max);
+        assert_eq!(path.theta_line_min, expected.theta_line_min);
+
+        path.remove_waypoint(2).unwrap();
+        assert_eq!(path.get_turn_radii(), vec![30.0]);
+    }
+
+    #[test]
+    fn auto_radius_is_kept_when_reversing() {
+        let points: Vec<Vector2<f64>> = short_leg().iter().map(|w| w.pos).collect();
+        let mut path =
+            WaypointPath::try_from_waypoints_auto_radius(short_leg(), 30.0, false).unwrap();
+        path.set_end_behavior(EndBehavior::Reverse);
+        drive_past_the_end(&mut path, &points);
+
+        assert_eq!(path.completed_laps(), 1);
+        assert_eq!(path.get_turn_radii(), vec![10.0, 10.0]);
+    }
+
+    #[test]
+    fn max_curvature_ahead_looks_past_short_legs() {
+        // a gentle corner, a short leg and a tight corner
+        let mut waypoints = to_waypoints(vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(50.0, 0.0),
+            Vector2::new(60.0, 10.0),
+            Vector2::new(0.0, 10.0),
+        ]);
+        waypoints[1].turn_radius = Some(10.0);
+        waypoints[2].turn_radius = Some(2.0);
+        let path = WaypointPath::try_from_waypoints(waypoints, 2.0, false).unwrap();
+
+        assert!((path.comp_max_curvature_ahead(40.0, 15.0) - 0.1).abs() < 1e-9);
+        assert!((path.comp_max_curvature_ahead(40.0, 30.0) - 0.5).abs() < 1e-9);
+    }
 }
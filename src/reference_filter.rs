@@ -0,0 +1,220 @@
+use crate::ilos::ssa;
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReferenceFilterError {
+    InvalidNaturalFrequency(f64),
+    InvalidDamping(f64),
+    InvalidSaturation(f64, f64),
+}
+
+impl fmt::Display for ReferenceFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReferenceFilterError::InvalidNaturalFrequency(w) => {
+                write!(f, "invalid natural frequency: {}", w)
+            }
+            ReferenceFilterError::InvalidDamping(zeta) => write!(f, "invalid damping: {}", zeta),
+            ReferenceFilterError::InvalidSaturation(rate, acc) => {
+                write!(f, "invalid saturation: ({}, {})", rate, acc)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReferenceFilterError {}
+
+// Third-order reference model: a first-order low-pass filter in series with a
+// mass-spring-damper, with saturation of the yaw rate and yaw acceleration.
+#[derive(Clone, Debug)]
+pub struct ReferenceFilter {
+    yaw: f64,
+    yaw_rate: f64,
+    yaw_acc: f64,
+    initialized: bool,
+    natural_frequency: f64,
+    damping: f64,
+    max_yaw_rate: f64,
+    max_yaw_acc: f64,
+}
+
+impl Default for ReferenceFilter {
+    fn default() -> Self {
+        Self::new(1.0, 1.0)
+    }
+}
+
+impl ReferenceFilter {
+    pub fn new(natural_frequency: f64, damping: f64) -> ReferenceFilter {
+        Self::try_new(natural_frequency, damping)
+            .unwrap_or_else(|e| panic!("Invalid reference filter: {}", e))
+    }
+
+    // the natural frequency and the damping have to be positive and finite
+    pub fn try_new(
+        natural_frequency: f64,
+        damping: f64,
+    ) -> Result<ReferenceFilter, ReferenceFilterError> {
+        if !(natural_frequency.is_finite() && natural_frequency > 0.0) {
+            return Err(ReferenceFilterError::InvalidNaturalFrequency(
+                natural_frequency,
+            ));
+        }
+        if !(damping.is_finite() && damping > 0.0) {
+            return Err(ReferenceFilterError::InvalidDamping(damping));
+        }
+        Ok(ReferenceFilter {
+            yaw: 0.0,
+            yaw_rate: 0.0,
+            yaw_acc: 0.0,
+            initialized: false,
+            natural_frequency,
+            damping,
+            max_yaw_rate: f64::INFINITY,
+            max_yaw_acc: f64::INFINITY,
+        })
+    }
+
+    // the limits have to be positive, infinity disables the saturation
+    pub fn set_saturation(
+        &mut self,
+        max_yaw_rate: f64,
+        max_yaw_acc: f64,
+    ) -> Result<(), ReferenceFilterError> {
+        // also rejects NaN
+        if !(max_yaw_rate > 0.0 && max_yaw_acc > 0.0) {
+            return Err(ReferenceFilterError::InvalidSaturation(
+                max_yaw_rate,
+                max_yaw_acc,
+            ));
+        }
+        self.max_yaw_rate = max_yaw_rate;
+        self.max_yaw_acc = max_yaw_acc;
+        Ok(())
+    }
+
+    pub fn update(&mut self, yaw_ref: f64, dt: f64) {
+        if !self.initialized {
+            self.yaw = ssa(yaw_ref);
+            self.initialized = true;
+        }
+        let w = self.natural_frequency;
+        let zeta = self.damping;
+
+        let yaw_jerk = w.powi(3) * ssa(yaw_ref - self.yaw)
+            - (2.0 * zeta + 1.0) * w.powi(2) * self.yaw_rate
+            - (2.0 * zeta + 1.0) * w * self.yaw_acc;
+
+        self.yaw_acc = (self.yaw_acc + yaw_jerk * dt).clamp(-self.max_yaw_acc, self.max_yaw_acc);
+        let yaw_rate = self.yaw_rate + self.yaw_acc * dt;
+        if yaw_rate.abs() > self.max_yaw_rate {
+            self.yaw_rate = yaw_rate.clamp(-self.max_yaw_rate, self.max_yaw_rate);
+            if self.yaw_acc * yaw_rate > 0.0 {
+                self.yaw_acc = 0.0;
+            }
+        } else {
+            self.yaw_rate = yaw_rate;
+        }
+        self.yaw = ssa(self.yaw + self.yaw_rate * dt);
+    }
+
+    pub fn reset(&mut self) {
+        self.yaw_rate = 0.0;
+        self.yaw_acc = 0.0;
+        self.initialized = false;
+    }
+
+    pub fn get_references(&self) -> (f64, f64, f64) {
+        (self.yaw, self.yaw_rate, self.yaw_acc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f64 = 0.01;
+
+    // steps from zero to the reference and returns the yaw after each update
+    fn comp_step_response(filter: &mut ReferenceFilter, yaw_ref: f64, duration: f64) -> Vec<f64> {
+        filter.update(0.0, DT);
+        (0..(duration / DT) as usize)
+            .map(|_| {
+                filter.update(yaw_ref, DT);
+                filter.get_references().0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn step_response_settles_without_overshoot() {
+        let mut filter = ReferenceFilter::new(1.0, 1.0);
+        let response = comp_step_response(&mut filter, 0.5, 20.0);
+
+        assert!(response.iter().all(|&yaw| yaw <= 0.5 + 1e-9));
+        assert!(response.windows(2).all(|w| w[1] >= w[0] - 1e-12));
+        let (yaw, yaw_rate, yaw_acc) = filter.get_references();
+        assert!((yaw - 0.5).abs() < 1e-3);
+        assert!(yaw_rate.abs() < 1e-3);
+        assert!(yaw_acc.abs() < 1e-3);
+    }
+
+    #[test]
+    fn step_response_respects_saturation() {
+        let mut filter = ReferenceFilter::new(2.0, 1.0);
+        filter.set_saturation(0.1, 0.05).unwrap();
+        filter.update(0.0, DT);
+        for _ in 0..(40.0 / DT) as usize {
+            filter.update(1.0, DT);
+            let (_, yaw_rate, yaw_acc) = filter.get_references();
+            assert!(yaw_rate.abs() <= 0.1 + 1e-12);
+            assert!(yaw_acc.abs() <= 0.05 + 1e-12);
+        }
+        assert!((filter.get_references().0 - 1.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn step_across_pi_takes_the_short_way() {
+        let pi = core::f64::consts::PI;
+        let mut filter = ReferenceFilter::new(1.0, 1.0);
+        filter.update(pi - 0.1, DT);
+        for _ in 0..(20.0 / DT) as usize {
+            filter.update(-pi + 0.1, DT);
+            assert!(filter.get_references().1 >= -1e-12);
+        }
+        assert!(ssa(filter.get_references().0 - (-pi + 0.1)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn first_update_starts_at_the_reference() {
+        let mut filter = ReferenceFilter::new(1.0, 1.0);
+        filter.update(1.0, DT);
+        assert!((filter.get_references().0 - 1.0).abs() < 1e-12);
+        filter.reset();
+        filter.update(-1.0, DT);
+        assert!((filter.get_references().0 + 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        assert_eq!(
+            ReferenceFilter::try_new(0.0, 1.0).err(),
+            Some(ReferenceFilterError::InvalidNaturalFrequency(0.0))
+        );
+        assert!(ReferenceFilter::try_new(f64::NAN, 1.0).is_err());
+        assert_eq!(
+            ReferenceFilter::try_new(1.0, -0.5).err(),
+            Some(ReferenceFilterError::InvalidDamping(-0.5))
+        );
+
+        let mut filter = ReferenceFilter::new(1.0, 1.0);
+        assert!(filter.set_saturation(-0.1, 0.05).is_err());
+        assert!(filter.set_saturation(0.1, f64::NAN).is_err());
+        // the rejected limits are not applied
+        filter.update(1.0, 0.1);
+        filter.update(2.0, 0.1);
+        assert!(filter.get_references().2 > 0.0);
+        assert!(filter.set_saturation(0.1, f64::INFINITY).is_ok());
+    }
+}
@@ -0,0 +1,96 @@
+extern crate nalgebra as na;
+
+use na::{Matrix2, Vector2};
+
+use crate::paths::path::Path;
+
+#[allow(non_snake_case)]
+#[derive(Clone, Debug)]
+pub struct SpeedAssignment {
+    surge_speed: f64,
+    path_speed: f64,
+    S: Matrix2<f64>,
+    nominal_speed: f64,
+    min_speed: f64,
+    max_lateral_acc: f64,
+    cross_track_gain: f64,
+    braking_distance: f64,
+}
+
+impl Default for SpeedAssignment {
+    fn default() -> Self {
+        Self::new(1.0, 0.2, 0.5)
+    }
+}
+
+impl SpeedAssignment {
+    pub fn new(nominal_speed: f64, min_speed: f64, max_lateral_acc: f64) -> SpeedAssignment {
+        SpeedAssignment {
+            surge_speed: 0.0,
+            path_speed: 0.0,
+            S: Matrix2::new(0.0, -1.0, 1.0, 0.0),
+            nominal_speed,
+            min_speed,
+            max_lateral_acc,
+            cross_track_gain: 0.1,
+            braking_distance: 10.0,
+        }
+    }
+
+    pub fn update<P: Path + ?Sized>(&mut self, path: &P, theta: f64, pos: &Vector2<f64>) {
+        let pos_d = path.comp_pos(theta);
+        let tau = path.comp_tangent(theta);
+        let cross_track_err = (pos - pos_d).dot(&(self.S * tau.into_inner()));
+        // the speed of the current leg replaces the nominal speed
+        let nominal_speed = path
+            .current_leg()
+            .and_then(|leg| leg.speed)
+            .unwrap_or(self.nominal_speed);
+
+        // once completed, slow down towards the final point and stop when it has been passed
+        if path.is_completed() {
+            let distance_ahead = (pos_d - pos).dot(&tau);
+            self.surge_speed =
+                nominal_speed * (distance_ahead / self.braking_distance).clamp(0.0, 1.0);
+            self.path_speed = 0.0;
+            return;
+        }
+
+        // limit the centripetal acceleration U^2 * kappa on the current and upcoming segments
+        let curvature = path.comp_max_curvature_ahead(theta, self.braking_distance);
+        let mut surge_speed = nominal_speed;
+        if curvature > 0.0 {
+            surge_speed = surge_speed.min((self.max_lateral_acc / curvature).sqrt());
+        }
+        surge_speed /= 1.0 + self.cross_track_gain * cross_track_err.abs();
+        self.surge_speed = surge_speed.clamp(self.min_speed.min(nominal_speed), nominal_speed);
+
+        let arc_length_derivative = path.comp_arc_length_derivative(theta);
+        self.path_speed = if arc_length_derivative > f64::EPSILON {
+            self.surge_speed / arc_length_derivative
+        } else {
+            0.0
+        };
+    }
+
+    pub fn set_nominal_speed(&mut self, nominal_speed: f64) {
+        self.nominal_speed = nominal_speed;
+    }
+
+    pub fn get_nominal_speed(&self) -> f64 {
+        self.nominal_speed
+    }
+
+    pub fn set_cross_track_gain(&mut self, cross_track_gain: f64) {
+        self.cross_track_gain = cross_track_gain;
+    }
+
+    pub fn set_braking_distance(&mut self, braking_distance: f64) {
+        self.braking_distance = braking_distance;
+    }
+
+    // desired surge speed and path-parameter rate
+    pub fn get_references(&self) -> (f64, f64) {
+        (self.surge_speed, self.path_speed)
+    }
+}
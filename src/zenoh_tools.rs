@@ -1,4 +1,14 @@
-use crate::{ilos::ILOS, paths::path::Path};
+use crate::{
+    alos::ALOS,
+    elos::ELOS,
+    gain_schedule::GainSchedule,
+    guidance_law::{GuidanceError, GuidanceLaw, GuidanceState, OutputMode, YawRateMode},
+    ilos::{AntiWindup, ILOS},
+    los::{Lookahead, LOS},
+    paths::path::{EndBehavior, Path},
+    reference_filter::ReferenceFilter,
+    speed_assignment::SpeedAssignment,
+};
 
 use cdr::{CdrLe, Infinite};
 use serde_derive::{Deserialize, Serialize};
@@ -6,60 +16,414 @@ use std::fmt;
 use std::sync::{Arc, Mutex};
 use zenoh::{prelude::r#async::*, publication::Publisher};
 
+use clap::ValueEnum;
 use tokio::select;
 extern crate nalgebra as na;
 use na::Vector2;
 
-pub async fn ilos_timer(
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum GuidanceLawType {
+    Ilos,
+    Los,
+    Alos,
+    Elos,
+}
+
+// The LOS laws use the lookahead shape with its nominal distance set to 1 / prop_gain
+pub fn new_guidance_law(
+    law_type: GuidanceLawType,
+    prop_gain: f64,
+    integral_gain: f64,
+    observer_gain: f64,
+    lookahead: Lookahead,
+    anti_windup: AntiWindup,
+) -> Result<Arc<Mutex<dyn GuidanceLaw + Send>>, GuidanceError> {
+    if law_type != GuidanceLawType::Ilos && anti_windup != AntiWindup::default() {
+        println!("Only ILOS has an integral state, ignoring the anti-windup settings");
+    }
+    let law: Arc<Mutex<dyn GuidanceLaw + Send>> = match law_type {
+        GuidanceLawType::Ilos => {
+            if let Lookahead::TimeVarying { .. } = lookahead {
+                println!("ILOS has no time-varying lookahead, ignoring it");
+            }
+            let mut ilos = ILOS::default();
+            ilos.set_anti_windup(anti_windup)?;
+            Arc::new(Mutex::new(ilos))
+        }
+        GuidanceLawType::Los => Arc::new(Mutex::new(LOS::try_new(lookahead)?)),
+        GuidanceLawType::Alos => {
+            let mut alos = ALOS::default();
+            alos.set_lookahead(lookahead)?;
+            Arc::new(Mutex::new(alos))
+        }
+        GuidanceLawType::Elos => {
+            let mut elos = ELOS::default();
+            elos.set_lookahead(lookahead)?;
+            elos.set_observer_gain(observer_gain);
+            Arc::new(Mutex::new(elos))
+        }
+    };
+    law.lock().unwrap().set_gains(prop_gain, integral_gain)?;
+    Ok(law)
+}
+
+#[derive(Clone, Debug)]
+pub struct TimerOptions {
+    pub reset_on_segment_change: bool,
+    pub output_mode: OutputMode,
+    pub yaw_rate_mode: YawRateMode,
+    pub reference_filter: Option<ReferenceFilter>,
+    pub filtered_topic_name: String,
+    pub speed_assignment: Option<SpeedAssignment>,
+    pub speed_topic_name: String,
+    pub completion_topic_name: String,
+    pub gain_schedule: Arc<Mutex<GainSchedule>>,
+}
+
+impl Default for TimerOptions {
+    fn default() -> Self {
+        TimerOptions {
+            reset_on_segment_change: false,
+            output_mode: OutputMode::Course,
+            yaw_rate_mode: YawRateMode::FiniteDifference,
+            reference_filter: None,
+            filtered_topic_name: "rt/yaw_refs_filtered".to_string(),
+            speed_assignment: None,
+            speed_topic_name: "rt/speed_refs".to_string(),
+            completion_topic_name: "rt/path_completed".to_string(),
+            gain_schedule: Arc::new(Mutex::new(GainSchedule::default())),
+        }
+    }
+}
+
+// Guidance law, reference filter, speed assignment and gain schedule options shared by the binaries
+#[derive(clap::Args, Clone, Debug)]
+pub struct GuidanceArgs {
+    /// Lookahead distance at large cross-track errors, enables the time-varying lookahead of the
+    /// LOS laws with 1 / kp on the path
+    #[arg(long)]
+    pub min_lookahead: Option<f64>,
+    /// Decay of the time-varying lookahead with the squared cross-track error
+    #[arg(long, default_value_t = 0.01)]
+    pub lookahead_decay: f64,
+    /// Gain of the ELOS drift observer
+    #[arg(long, default_value_t = 1.0)]
+    pub observer_gain: f64,
+    /// Guidance law
+    #[arg(long, value_enum, default_value_t = GuidanceLawType::Ilos)]
+    pub law: GuidanceLawType,
+    /// Saturation limit of the ILOS integral state
+    #[arg(long)]
+    pub integral_limit: Option<f64>,
+    /// Cross-track error above which the ILOS integral state is frozen
+    #[arg(long)]
+    pub integration_threshold: Option<f64>,
+    /// YAML file with gain schedules keyed on surge speed and segment type
+    #[arg(long)]
+    pub gain_schedule: Option<String>,
+    /// File the guidance law state is restored from at startup and persisted to
+    #[arg(long)]
+    pub state_file: Option<String>,
+    /// Period in seconds between writes of the guidance law state
+    #[arg(long, default_value_t = 1.0)]
+    pub state_save_period: f64,
+    /// Publish the desired course or the sideslip-compensated heading, ALOS and ELOS compensate
+    /// the sideslip themselves and always publish a heading
+    #[arg(long, value_enum, default_value_t = OutputMode::Course)]
+    pub output_mode: OutputMode,
+    /// Compute the yaw rate reference by finite differencing or analytically from the path curvature
+    #[arg(long, value_enum, default_value_t = YawRateMode::FiniteDifference)]
+    pub yaw_rate_mode: YawRateMode,
+    /// Natural frequency of the yaw reference filter, enables the filter
+    #[arg(long)]
+    pub filter_freq: Option<f64>,
+    /// Relative damping of the yaw reference filter
+    #[arg(long, default_value_t = 1.0)]
+    pub filter_damping: f64,
+    /// Yaw rate saturation of the reference filter
+    #[arg(long, default_value_t = f64::INFINITY)]
+    pub max_yaw_rate: f64,
+    /// Yaw acceleration saturation of the reference filter
+    #[arg(long, default_value_t = f64::INFINITY)]
+    pub max_yaw_acc: f64,
+    /// Output filtered ILOS message topic name
+    #[arg(long, default_value = "rt/yaw_refs_filtered")]
+    pub filtered_topic: String,
+    /// Nominal surge speed, enables speed assignment
+    #[arg(long)]
+    pub nominal_speed: Option<f64>,
+    /// Minimum assigned surge speed
+    #[arg(long, default_value_t = 0.2)]
+    pub min_speed: f64,
+    /// Maximum lateral acceleration used to slow down in turns
+    #[arg(long, default_value_t = 0.5)]
+    pub max_lateral_acc: f64,
+    /// Output speed message topic name
+    #[arg(long, default_value = "rt/speed_refs")]
+    pub speed_topic: String,
+    /// Output path completion message topic name
+    #[arg(long, default_value = "rt/path_completed")]
+    pub completion_topic: String,
+}
+
+// Builds the guidance law and the timer options from the arguments, panicking on invalid ones.
+// The law state is restored from and persisted to the state file, so this must run on the runtime
+pub fn setup_guidance(
+    args: GuidanceArgs,
+    prop_gain: f64,
+    integral_gain: f64,
+) -> (Arc<Mutex<dyn GuidanceLaw + Send>>, TimerOptions) {
+    let anti_windup = AntiWindup {
+        integral_limit: args.integral_limit,
+        integration_threshold: args.integration_threshold,
+    };
+    let lookahead = match args.min_lookahead {
+        Some(delta_min) => {
+            Lookahead::try_time_varying(delta_min, 1.0 / prop_gain, args.lookahead_decay)
+        }
+        None => Lookahead::try_constant(1.0 / prop_gain),
+    }
+    .unwrap_or_else(|e| panic!("Invalid lookahead: {}", e));
+    let law = new_guidance_law(
+        args.law,
+        prop_gain,
+        integral_gain,
+        args.observer_gain,
+        lookahead,
+        anti_windup,
+    )
+    .unwrap_or_else(|e| panic!("Invalid guidance law: {}", e));
+
+    if let Some(state_file) = args.state_file {
+        restore_guidance_state(&law, &state_file);
+
+        let a_law = law.clone();
+        let state_save_period = args.state_save_period;
+        tokio::spawn(async move {
+            persist_guidance_state(a_law, state_file, state_save_period).await;
+        });
+    }
+
+    let gain_schedule = match &args.gain_schedule {
+        Some(file_name) => load_gain_schedule(file_name),
+        None => GainSchedule::default(),
+    };
+    let reference_filter = args.filter_freq.map(|freq| {
+        let mut filter = ReferenceFilter::try_new(freq, args.filter_damping)
+            .unwrap_or_else(|e| panic!("Invalid reference filter: {}", e));
+        filter
+            .set_saturation(args.max_yaw_rate, args.max_yaw_acc)
+            .unwrap_or_else(|e| panic!("Invalid reference filter: {}", e));
+        filter
+    });
+    let speed_assignment = args
+        .nominal_speed
+        .map(|speed| SpeedAssignment::new(speed, args.min_speed, args.max_lateral_acc));
+
+    let options = TimerOptions {
+        reset_on_segment_change: false,
+        output_mode: args.output_mode,
+        yaw_rate_mode: args.yaw_rate_mode,
+        reference_filter,
+        filtered_topic_name: args.filtered_topic,
+        speed_assignment,
+        speed_topic_name: args.speed_topic,
+        completion_topic_name: args.completion_topic,
+        gain_schedule: Arc::new(Mutex::new(gain_schedule)),
+    };
+    (law, options)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VesselState {
+    pub pos: Vector2<f64>,
+    pub yaw: f64,
+    // body-fixed linear velocity (surge, sway)
+    pub vel: Vector2<f64>,
+}
+
+pub async fn ilos_timer<G: GuidanceLaw + Send + ?Sized>(
     session: Arc<Session>,
     topic_name: String,
-    arc_pos: Arc<Mutex<Option<Vector2<f64>>>>,
-    ilos: Arc<Mutex<ILOS>>,
+    arc_state: Arc<Mutex<Option<VesselState>>>,
+    ilos: Arc<Mutex<G>>,
     mut path: impl Path,
     dt: f64,
+    mut options: TimerOptions,
 ) {
     let publisher = session.declare_publisher(topic_name).res().await.unwrap();
+    let filtered_publisher = match options.reference_filter {
+        Some(_) => Some(
+            session
+                .declare_publisher(options.filtered_topic_name.clone())
+                .res()
+                .await
+                .unwrap(),
+        ),
+        None => None,
+    };
+    let speed_publisher = match options.speed_assignment {
+        Some(_) => Some(
+            session
+                .declare_publisher(options.speed_topic_name.clone())
+                .res()
+                .await
+                .unwrap(),
+        ),
+        None => None,
+    };
+    let completion_publisher = session
+        .declare_publisher(options.completion_topic_name.clone())
+        .res()
+        .await
+        .unwrap();
+
+    if options.output_mode == OutputMode::Course
+        && ilos.lock().unwrap().get_output_mode() == OutputMode::Heading
+    {
+        println!("The guidance law compensates the sideslip itself, publishing heading references");
+    }
 
+    let mut applied_schedule = None;
+    let mut laps_prev = path.completed_laps();
+    let end_behavior = path.get_end_behavior();
     let mut timer = tokio::time::interval(tokio::time::Duration::from_secs_f64(dt));
     loop {
         timer.tick().await;
 
-        let pos = {
-            let pos_guard = arc_pos.lock().unwrap();
-            if *pos_guard == None {
+        let state = {
+            let state_guard = arc_state.lock().unwrap();
+            if *state_guard == None {
                 continue;
             }
-            pos_guard.unwrap()
+            state_guard.unwrap()
         };
-        let theta = path.comp_theta(&pos);
+        let segment_prev = path.current_segment();
+        let completed_prev = path.is_completed();
+        let theta = path.comp_theta(&state.pos);
+        let segment_changed = path.current_segment() != segment_prev;
+        if path.is_completed() && !completed_prev {
+            if path.get_end_behavior() != end_behavior {
+                println!("Could not start the path over, stopping at its end");
+            }
+            println!("Path completed");
+            publish_completion_message(&completion_publisher, path.total_length(), false).await;
+        }
+        if path.completed_laps() > laps_prev {
+            println!("Lap completed");
+            publish_completion_message(&completion_publisher, path.total_length(), true).await;
+        }
+        laps_prev = path.completed_laps();
+
+        if let (Some(speed_assignment), Some(speed_publisher)) =
+            (options.speed_assignment.as_mut(), speed_publisher.as_ref())
+        {
+            speed_assignment.update(&path, theta, &state.pos);
+            let (surge_speed, path_speed) = speed_assignment.get_references();
+            publish_speed_message(speed_publisher, surge_speed, path_speed).await;
+        }
+
+        // a stopped path only brakes, the yaw is left to the vessel
+        if path.is_completed() && path.get_end_behavior() == EndBehavior::Stop {
+            continue;
+        }
+
         let pos_desired = path.comp_pos(theta);
         let tau_desired = path.comp_tangent(theta);
 
-        let (yaw, yaw_rate) = {
+        // the velocity is body-fixed, so vel[0] is the surge speed the schedule is keyed on
+        let segment_type = path.current_segment_type();
+        let scheduled_gains = options
+            .gain_schedule
+            .lock()
+            .unwrap()
+            .comp_gains(state.vel[0], segment_type);
+        // only set when the scheduled gains or the segment type change
+        let schedule = scheduled_gains.map(|gains| (gains, segment_type));
+        let scheduled_gains = if schedule != applied_schedule {
+            scheduled_gains
+        } else {
+            None
+        };
+
+        let references = {
             let mut ilos = ilos.lock().unwrap();
-            ilos.update(&pos, &pos_desired, &tau_desired, dt);
-            ilos.get_references()
+            // the gains and the reset are part of the checked update, a rejected cycle restores
+            // the state from before them
+            let snapshot = ilos.save_state();
+            if let Some((kp, ki)) = scheduled_gains {
+                if let Err(e) = ilos.set_gains(kp, ki) {
+                    println!("Ignoring scheduled gains: {}", e);
+                }
+            }
+            if options.reset_on_segment_change && segment_changed {
+                ilos.reset();
+            }
+            ilos.set_yaw_rate_mode(options.yaw_rate_mode);
+            ilos.set_velocity(state.yaw, &state.vel);
+            ilos.set_curvature(path.comp_curvature(theta));
+            match ilos.try_update(&state.pos, &pos_desired, &tau_desired, dt) {
+                Ok(()) => {
+                    applied_schedule = schedule;
+                    let (yaw, yaw_rate) = ilos.get_references();
+                    Ok((yaw, yaw_rate, ilos.get_output_mode()))
+                }
+                Err(e) => ilos.restore_state(&snapshot).and(Err(e)),
+            }
+        };
+        let (yaw, yaw_rate, law_output_mode) = match references {
+            Ok(references) => references,
+            Err(e) => {
+                println!("Skipping guidance update: {}", e);
+                continue;
+            }
+        };
+        // laws yielding a heading have compensated the sideslip already
+        let (yaw, output_mode) = match law_output_mode {
+            OutputMode::Course => (
+                options.output_mode.comp_yaw(yaw, &state.vel),
+                options.output_mode,
+            ),
+            OutputMode::Heading => (yaw, OutputMode::Heading),
         };
-        publish_ilos_message(&publisher, yaw, yaw_rate).await;
+        publish_ilos_message(&publisher, yaw, yaw_rate, output_mode).await;
+
+        if let (Some(reference_filter), Some(filtered_publisher)) = (
+            options.reference_filter.as_mut(),
+            filtered_publisher.as_ref(),
+        ) {
+            reference_filter.update(yaw, dt);
+            let (yaw, yaw_rate, yaw_acc) = reference_filter.get_references();
+            publish_filtered_ilos_message(filtered_publisher, yaw, yaw_rate, yaw_acc, output_mode)
+                .await;
+        }
     }
 }
 
-pub async fn publish_ilos_message(publisher: &Publisher<'_>, yaw: f64, yaw_rate: f64) {
+fn header_now() -> Header {
     let t_now = std::time::SystemTime::now();
     let since_epoch = t_now.duration_since(std::time::UNIX_EPOCH).unwrap();
 
-    let header = Header {
+    Header {
         stamp: Time {
             sec: since_epoch.as_secs() as i32,
             nanosec: since_epoch.subsec_nanos(),
         },
         frame_id: "".to_string(),
-    };
+    }
+}
 
+pub async fn publish_ilos_message(
+    publisher: &Publisher<'_>,
+    yaw: f64,
+    yaw_rate: f64,
+    output_mode: OutputMode,
+) {
     let ilos_msg = ILOSMessage {
-        header,
+        header: header_now(),
         yaw,
         yaw_rate,
+        mode: output_mode as u8,
     };
 
     let encoded = cdr::serialize::<_, _, CdrLe>(&ilos_msg, Infinite).unwrap();
@@ -68,10 +432,58 @@ pub async fn publish_ilos_message(publisher: &Publisher<'_>, yaw: f64, yaw_rate:
     }
 }
 
+pub async fn publish_filtered_ilos_message(
+    publisher: &Publisher<'_>,
+    yaw: f64,
+    yaw_rate: f64,
+    yaw_acc: f64,
+    output_mode: OutputMode,
+) {
+    let filtered_msg = ILOSFilteredMessage {
+        header: header_now(),
+        yaw,
+        yaw_rate,
+        yaw_acc,
+        mode: output_mode as u8,
+    };
+
+    let encoded = cdr::serialize::<_, _, CdrLe>(&filtered_msg, Infinite).unwrap();
+    if let Err(e) = publisher.put(encoded).res().await {
+        println!("Error writing {}: {}", publisher.key_expr().as_str(), e);
+    }
+}
+
+pub async fn publish_speed_message(publisher: &Publisher<'_>, surge_speed: f64, path_speed: f64) {
+    let speed_msg = SpeedMessage {
+        header: header_now(),
+        surge_speed,
+        path_speed,
+    };
+
+    let encoded = cdr::serialize::<_, _, CdrLe>(&speed_msg, Infinite).unwrap();
+    if let Err(e) = publisher.put(encoded).res().await {
+        println!("Error writing {}: {}", publisher.key_expr().as_str(), e);
+    }
+}
+
+// lap is set when a looping or reversing path starts over instead of ending
+pub async fn publish_completion_message(publisher: &Publisher<'_>, total_length: f64, lap: bool) {
+    let completion_msg = PathCompletedMessage {
+        header: header_now(),
+        total_length,
+        lap,
+    };
+
+    let encoded = cdr::serialize::<_, _, CdrLe>(&completion_msg, Infinite).unwrap();
+    if let Err(e) = publisher.put(encoded).res().await {
+        println!("Error writing {}: {}", publisher.key_expr().as_str(), e);
+    }
+}
+
 pub async fn position_subscriber(
     session: Arc<Session>,
     topic_name: String,
-    arc_pos: Arc<Mutex<Option<Vector2<f64>>>>,
+    arc_state: Arc<Mutex<Option<VesselState>>>,
 ) {
     let subscriber = session.declare_subscriber(topic_name).res().await.unwrap();
 
@@ -81,31 +493,73 @@ pub async fn position_subscriber(
             cdr::size::Infinite,
         ) {
             Ok(odom) => {
-                let pos = Vector2::new(odom.pose.pose.position.x, odom.pose.pose.position.y);
-                let mut pos_ref = arc_pos.lock().unwrap();
-                *pos_ref = Some(pos);
+                let state = VesselState {
+                    pos: Vector2::new(odom.pose.pose.position.x, odom.pose.pose.position.y),
+                    yaw: odom.pose.pose.orientation.yaw(),
+                    vel: Vector2::new(odom.twist.twist.linear.x, odom.twist.twist.linear.y),
+                };
+                let mut state_ref = arc_state.lock().unwrap();
+                *state_ref = Some(state);
             }
             Err(e) => println!("Error decoding Odometry msg: {}", e),
         }
     }
 }
 
-pub async fn update_ilos_parameters(
+pub fn restore_guidance_state<G: GuidanceLaw + Send + ?Sized>(
+    ilos: &Arc<Mutex<G>>,
+    file_name: &str,
+) {
+    let data = match std::fs::read_to_string(file_name) {
+        Ok(data) => data,
+        Err(e) => {
+            println!("No guidance state restored from '{}': {}", file_name, e);
+            return;
+        }
+    };
+    match serde_json::from_str::<GuidanceState>(&data) {
+        Ok(state) => match ilos.lock().unwrap().restore_state(&state) {
+            Ok(()) => println!("Restored guidance state from '{}'", file_name),
+            Err(e) => println!("Error restoring guidance state: {}", e),
+        },
+        Err(e) => println!("Error decoding guidance state: {}", e),
+    }
+}
+
+pub async fn persist_guidance_state<G: GuidanceLaw + Send + ?Sized>(
+    ilos: Arc<Mutex<G>>,
+    file_name: String,
+    period: f64,
+) {
+    let tmp_file_name = format!("{}.tmp", file_name);
+
+    let mut timer = tokio::time::interval(tokio::time::Duration::from_secs_f64(period));
+    loop {
+        timer.tick().await;
+
+        let state = ilos.lock().unwrap().save_state();
+        let encoded = serde_json::to_string(&state).unwrap();
+        if let Err(e) = std::fs::write(&tmp_file_name, encoded)
+            .and_then(|_| std::fs::rename(&tmp_file_name, &file_name))
+        {
+            println!("Error writing guidance state to '{}': {}", file_name, e);
+        }
+    }
+}
+
+pub fn load_gain_schedule(file_name: &str) -> GainSchedule {
+    let f = std::fs::File::open(file_name).expect("Could not open gain schedule file.");
+    serde_yaml::from_reader(f).expect("Could not parse gain schedule file.")
+}
+
+pub async fn update_ilos_parameters<G: GuidanceLaw + Send + ?Sized>(
     session: Arc<Session>,
     key_expr: String,
-    ilos: Arc<Mutex<ILOS>>,
+    ilos: Arc<Mutex<G>>,
+    gain_schedule: Arc<Mutex<GainSchedule>>,
 ) {
     let key_expr = KeyExpr::try_from(key_expr).unwrap();
 
-    let (kp, ki) = {
-        let ilos = ilos.lock().unwrap();
-        ilos.get_gains()
-    };
-    let mut ilos_params = ILOSParameters {
-        proportional_gain: kp,
-        integral_gain: ki,
-    };
-
     println!("Declaring Parameter Subscriber on '{key_expr}'...");
     let subscriber = session.declare_subscriber(&key_expr).res().await.unwrap();
 
@@ -122,12 +576,31 @@ pub async fn update_ilos_parameters(
             sample = subscriber.recv_async() => {
                 let sample = sample.unwrap();
                 let data = sample.value.payload.contiguous().into_owned();
-                match serde_json::from_str(String::from_utf8(data).unwrap().as_str()) {
-                    Ok(params) => {
-                        ilos_params = params;
-                        let mut ilos = ilos.lock().unwrap();
+                match serde_json::from_str::<ILOSParameters>(String::from_utf8(data).unwrap().as_str()) {
+                    Ok(ilos_params) => {
                         println!(">> [Subscriber] Received ILOS Parameters: {:?}", ilos_params);
-                        ilos.set_gains(ilos_params.proportional_gain, ilos_params.integral_gain);
+                        let mut ilos = ilos.lock().unwrap();
+                        let (kp, ki) = ilos.get_gains();
+                        if let Err(e) = ilos.set_gains(
+                            ilos_params.proportional_gain.unwrap_or(kp),
+                            ilos_params.integral_gain.unwrap_or(ki),
+                        ) {
+                            println!("Error setting ILOS parameters: {}", e);
+                        }
+                        match ilos_params.gain_schedule {
+                            Some(schedule) => *gain_schedule.lock().unwrap() = schedule,
+                            // gains set by hand would be overridden by the schedule
+                            None if ilos_params.proportional_gain.is_some()
+                                || ilos_params.integral_gain.is_some() =>
+                            {
+                                let mut gain_schedule = gain_schedule.lock().unwrap();
+                                if *gain_schedule != GainSchedule::default() {
+                                    println!("Gain schedule disabled by the new gains");
+                                    *gain_schedule = GainSchedule::default();
+                                }
+                            }
+                            None => {}
+                        }
                     }
                     Err(e) => println!("Error decoding ILOS parameter msg: {}", e),
                 }
@@ -137,6 +610,12 @@ pub async fn update_ilos_parameters(
                 let query = query.unwrap();
                 println!(">> [Queryable ] Received Query '{}'", query.selector());
 
+                let (kp, ki) = ilos.lock().unwrap().get_gains();
+                let ilos_params = ILOSParameters {
+                    proportional_gain: Some(kp),
+                    integral_gain: Some(ki),
+                    gain_schedule: Some(gain_schedule.lock().unwrap().clone()),
+                };
                 let encoded = serde_json::to_string(&ilos_params).unwrap().into_bytes();
 
                 let mut value = Value::empty();
@@ -152,8 +631,12 @@ pub async fn update_ilos_parameters(
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 struct ILOSParameters {
-    proportional_gain: f64,
-    integral_gain: f64,
+    #[serde(default)]
+    proportional_gain: Option<f64>,
+    #[serde(default)]
+    integral_gain: Option<f64>,
+    #[serde(default)]
+    gain_schedule: Option<GainSchedule>,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -201,6 +684,13 @@ struct Quaternion {
     w: f64,
 }
 
+impl Quaternion {
+    fn yaw(&self) -> f64 {
+        (2.0 * (self.w * self.z + self.x * self.y))
+            .atan2(1.0 - 2.0 * (self.y.powi(2) + self.z.powi(2)))
+    }
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 struct Pose {
     position: Point,
@@ -230,6 +720,32 @@ struct ILOSMessage {
     header: Header,
     yaw: f64,
     yaw_rate: f64,
+    // 0: course, 1: heading
+    mode: u8,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+struct ILOSFilteredMessage {
+    header: Header,
+    yaw: f64,
+    yaw_rate: f64,
+    yaw_acc: f64,
+    // 0: course, 1: heading
+    mode: u8,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+struct SpeedMessage {
+    header: Header,
+    surge_speed: f64,
+    path_speed: f64,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+struct PathCompletedMessage {
+    header: Header,
+    total_length: f64,
+    lap: bool,
 }
 
 #[derive(Deserialize, PartialEq)]